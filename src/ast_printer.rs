@@ -0,0 +1,121 @@
+use crate::expr::Expr;
+use crate::stmt::Stmt;
+use crate::token::Token;
+
+/// Renders scanned tokens and parsed statements back into readable text, for
+/// verifying the scanner/parser without running the program through the
+/// `Interpreter`. Mirrors the direct-match style `Resolver` uses over the
+/// `Expr`/`Stmt` trees rather than a separate visitor trait.
+pub struct AstPrinter;
+
+impl Default for AstPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AstPrinter {
+    pub fn new() -> Self {
+        AstPrinter
+    }
+
+    /// Prints every token on its own line via `Token`'s `Display`, e.g. for a
+    /// `--tokens` debug run that stops after scanning.
+    pub fn print_tokens(&self, tokens: &[Token]) -> String {
+        tokens.iter().map(|token| token.to_string()).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Renders every statement in `program`, one per line, for a `--ast`
+    /// debug run that stops after parsing.
+    pub fn print(&self, program: &[Stmt]) -> String {
+        program.iter().map(|stmt| self.print_stmt(stmt)).collect::<Vec<_>>().join("\n")
+    }
+
+    fn print_stmt(&self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Expression { expression } => self.print_expr(expression),
+
+            Stmt::Print { expression } => self.parenthesize("print", &[expression]),
+
+            Stmt::Var { name, initializer } => match initializer {
+                Some(init) => self.parenthesize(&format!("var {}", name.lexeme), &[init]),
+                None => format!("(var {})", name.lexeme),
+            },
+
+            Stmt::Block { statements } => {
+                let body = statements.iter().map(|s| self.print_stmt(s)).collect::<Vec<_>>().join(" ");
+                format!("(block {body})")
+            }
+
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let then = self.print_stmt(then_branch);
+                match else_branch.as_ref() {
+                    Some(else_branch) => {
+                        format!(
+                            "(if {} {} {})",
+                            self.print_expr(condition),
+                            then,
+                            self.print_stmt(else_branch)
+                        )
+                    }
+                    None => format!("(if {} {})", self.print_expr(condition), then),
+                }
+            }
+
+            Stmt::While { condition, body, .. } => {
+                format!("(while {} {})", self.print_expr(condition), self.print_stmt(body))
+            }
+
+            Stmt::Function { name, params, body } => {
+                let params = params.iter().map(|p| p.lexeme.as_str()).collect::<Vec<_>>().join(" ");
+                let body = body.iter().map(|s| self.print_stmt(s)).collect::<Vec<_>>().join(" ");
+                format!("(fun {} ({}) {})", name.lexeme, params, body)
+            }
+
+            Stmt::Return { value, .. } => match value {
+                Some(value) => self.parenthesize("return", &[value]),
+                None => "(return)".to_string(),
+            },
+
+            Stmt::Break { .. } => "(break)".to_string(),
+            Stmt::Continue { .. } => "(continue)".to_string(),
+        }
+    }
+
+    fn print_expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Literal { value } => match value {
+                Some(value) => value.to_string(),
+                None => "nil".to_string(),
+            },
+
+            Expr::Grouping { expression } => self.parenthesize("group", &[expression]),
+
+            Expr::Unary { operator, right } => self.parenthesize(&operator.lexeme, &[right]),
+
+            Expr::Binary { left, operator, right } => self.parenthesize(&operator.lexeme, &[left, right]),
+
+            Expr::Logical { left, operator, right } => self.parenthesize(&operator.lexeme, &[left, right]),
+
+            Expr::Variable { name, .. } => name.lexeme.clone(),
+
+            Expr::Assign { name, value, .. } => self.parenthesize(&format!("= {}", name.lexeme), &[value]),
+
+            Expr::Call { callee, arguments, .. } => {
+                let mut parts = vec![self.print_expr(callee)];
+                parts.extend(arguments.iter().map(|arg| self.print_expr(arg)));
+                format!("(call {})", parts.join(" "))
+            }
+        }
+    }
+
+    fn parenthesize(&self, name: &str, exprs: &[&Expr]) -> String {
+        let mut parts = vec![name.to_string()];
+        parts.extend(exprs.iter().map(|e| self.print_expr(e)));
+        format!("({})", parts.join(" "))
+    }
+}
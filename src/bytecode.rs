@@ -0,0 +1,719 @@
+//! Single-pass bytecode compiler and stack-based virtual machine.
+//!
+//! This is a second execution backend alongside the tree-walking
+//! `Parser`/`Interpreter` pair. It consumes the exact `Vec<Token>` produced by
+//! `Scanner::scan_tokens` and compiles straight to bytecode without ever
+//! building an AST, then runs that bytecode on a `Vm`.
+//!
+//! This backend only covers expressions, `print`, and global variables so
+//! far -- calls aren't wired up yet: `TokenType::LeftParen` has no infix
+//! `ParseRule`, so `Compiler` never emits `OpCode::Call` in the first place,
+//! and `Vm::run`'s `OpCode::Call` arm is a no-op should one ever reach it.
+
+use std::collections::HashMap;
+
+use crate::errors::{BytecodeErrorType, LoxResult};
+use crate::interner::Symbol;
+use crate::token::Object;
+use crate::token::Token;
+use crate::token_type::TokenType;
+
+/// A single instruction understood by the `Vm`.
+///
+/// Stored in a `Chunk` as raw bytes (`OpCode as u8`); operands such as
+/// constant/jump indices follow the opcode byte in the `Chunk`'s code array.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OpCode {
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    DefineGlobal,
+    GetGlobal,
+    SetGlobal,
+    Equal,
+    Greater,
+    Less,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Not,
+    Negate,
+    Print,
+    Jump,
+    JumpIfFalse,
+    Loop,
+    Call,
+    Return,
+}
+
+impl From<OpCode> for u8 {
+    fn from(op: OpCode) -> Self {
+        op as u8
+    }
+}
+
+impl TryFrom<u8> for OpCode {
+    type Error = ();
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        // Safety net: every variant above is listed here so decoding a
+        // corrupt chunk fails instead of transmuting garbage.
+        Ok(match byte {
+            0 => OpCode::Constant,
+            1 => OpCode::Nil,
+            2 => OpCode::True,
+            3 => OpCode::False,
+            4 => OpCode::Pop,
+            5 => OpCode::DefineGlobal,
+            6 => OpCode::GetGlobal,
+            7 => OpCode::SetGlobal,
+            8 => OpCode::Equal,
+            9 => OpCode::Greater,
+            10 => OpCode::Less,
+            11 => OpCode::Add,
+            12 => OpCode::Subtract,
+            13 => OpCode::Multiply,
+            14 => OpCode::Divide,
+            15 => OpCode::Not,
+            16 => OpCode::Negate,
+            17 => OpCode::Print,
+            18 => OpCode::Jump,
+            19 => OpCode::JumpIfFalse,
+            20 => OpCode::Loop,
+            21 => OpCode::Call,
+            22 => OpCode::Return,
+            _ => return Err(()),
+        })
+    }
+}
+
+/// A compiled sequence of instructions: the raw opcode/operand bytes, the
+/// constant pool they index into, and a line table parallel to `code` used
+/// to point runtime errors back at source.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Object>,
+    lines: Vec<usize>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Chunk {
+            code: Vec::new(),
+            constants: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Appends a raw byte (an opcode or an operand) at the given source line.
+    pub fn write_byte(&mut self, byte: u8, line: usize) {
+        self.code.push(byte);
+        self.lines.push(line);
+    }
+
+    pub fn write_op(&mut self, op: OpCode, line: usize) {
+        self.write_byte(op.into(), line);
+    }
+
+    /// Adds a value to the constant pool and returns its index, erroring if
+    /// the pool has grown past what a single byte operand can address.
+    pub fn add_constant(&mut self, value: Object, token: &Token) -> Result<u8, LoxResult> {
+        if self.constants.len() > u8::MAX as usize {
+            return Err(LoxResult::Bytecode {
+                token: token.dup(),
+                error_type: BytecodeErrorType::TooManyConstants,
+                msg: "".to_string(),
+            });
+        }
+
+        self.constants.push(value);
+        Ok((self.constants.len() - 1) as u8)
+    }
+
+    pub fn line_at(&self, offset: usize) -> usize {
+        self.lines[offset]
+    }
+}
+
+/// Precedence ladder driving the compiler's Pratt parser, from loosest to
+/// tightest binding.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    None,
+    Assignment,
+    Or,
+    And,
+    Equality,
+    Comparison,
+    Term,
+    Factor,
+    Unary,
+    Call,
+    Primary,
+}
+
+impl Precedence {
+    fn next(self) -> Precedence {
+        match self {
+            Precedence::None => Precedence::Assignment,
+            Precedence::Assignment => Precedence::Or,
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary => Precedence::Call,
+            Precedence::Call => Precedence::Primary,
+            Precedence::Primary => Precedence::Primary,
+        }
+    }
+}
+
+type ParseFn<'a> = fn(&mut Compiler<'a>) -> Result<(), LoxResult>;
+
+#[derive(Copy, Clone)]
+struct ParseRule<'a> {
+    prefix: Option<ParseFn<'a>>,
+    infix: Option<ParseFn<'a>>,
+    precedence: Precedence,
+}
+
+/// Drives Pratt-style precedence parsing directly over the token stream,
+/// emitting opcodes into a `Chunk` as it goes rather than building an AST.
+pub struct Compiler<'a> {
+    tokens: &'a [Token],
+    current: usize,
+    chunk: Chunk,
+}
+
+impl<'a> Compiler<'a> {
+    pub fn new(tokens: &'a [Token]) -> Self {
+        Compiler {
+            tokens,
+            current: 0,
+            chunk: Chunk::new(),
+        }
+    }
+
+    /// Compiles the whole token stream into a `Chunk`, consuming the
+    /// compiler.
+    pub fn compile(mut self) -> Result<Chunk, LoxResult> {
+        while !self.is_at_end() {
+            self.declaration()?;
+        }
+
+        self.emit(OpCode::Return);
+
+        Ok(self.chunk)
+    }
+
+    fn declaration(&mut self) -> Result<(), LoxResult> {
+        self.statement()
+    }
+
+    fn statement(&mut self) -> Result<(), LoxResult> {
+        if self.matches(TokenType::Print) {
+            self.expression()?;
+            self.emit(OpCode::Print);
+        } else {
+            self.expression()?;
+            self.emit(OpCode::Pop);
+        }
+
+        self.consume(TokenType::Semicolon, "Expected ';' after statement.")?;
+
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<(), LoxResult> {
+        self.parse_precedence(Precedence::Assignment)
+    }
+
+    fn parse_precedence(&mut self, precedence: Precedence) -> Result<(), LoxResult> {
+        let prefix = Self::rule(self.peek().ttype).prefix;
+
+        match prefix {
+            Some(prefix) => prefix(self)?,
+            None => {
+                return Err(LoxResult::Bytecode {
+                    token: self.peek().dup(),
+                    error_type: BytecodeErrorType::UnexpectedToken,
+                    msg: "Expected an expression here.".to_string(),
+                })
+            }
+        }
+
+        while precedence <= Self::rule(self.peek().ttype).precedence {
+            let infix = Self::rule(self.peek().ttype).infix.unwrap();
+            infix(self)?;
+        }
+
+        Ok(())
+    }
+
+    fn binary(&mut self) -> Result<(), LoxResult> {
+        let operator = self.advance().ttype;
+        let line = self.previous_line();
+        let rule = Self::rule(operator);
+
+        self.parse_precedence(rule.precedence.next())?;
+
+        match operator {
+            TokenType::Plus => self.emit_at(OpCode::Add, line),
+            TokenType::Minus => self.emit_at(OpCode::Subtract, line),
+            TokenType::Star => self.emit_at(OpCode::Multiply, line),
+            TokenType::Slash => self.emit_at(OpCode::Divide, line),
+            TokenType::BangEqual => {
+                self.emit_at(OpCode::Equal, line);
+                self.emit_at(OpCode::Not, line);
+            }
+            TokenType::EqualEqual => self.emit_at(OpCode::Equal, line),
+            TokenType::Greater => self.emit_at(OpCode::Greater, line),
+            TokenType::GreaterEqual => {
+                self.emit_at(OpCode::Less, line);
+                self.emit_at(OpCode::Not, line);
+            }
+            TokenType::Less => self.emit_at(OpCode::Less, line),
+            TokenType::LessEqual => {
+                self.emit_at(OpCode::Greater, line);
+                self.emit_at(OpCode::Not, line);
+            }
+            _ => unreachable!("binary() only called on binary operator tokens"),
+        }
+
+        Ok(())
+    }
+
+    fn unary(&mut self) -> Result<(), LoxResult> {
+        let operator = self.advance().ttype;
+        let line = self.previous_line();
+
+        self.parse_precedence(Precedence::Unary)?;
+
+        match operator {
+            TokenType::Minus => self.emit_at(OpCode::Negate, line),
+            TokenType::Bang => self.emit_at(OpCode::Not, line),
+            _ => unreachable!("unary() only called on unary operator tokens"),
+        }
+
+        Ok(())
+    }
+
+    fn grouping(&mut self) -> Result<(), LoxResult> {
+        self.expression()?;
+        self.consume(TokenType::RightParen, "Expected ')' after expression.")?;
+        Ok(())
+    }
+
+    /// Compiles the right-hand side of `left and right`: short-circuits by jumping
+    /// straight past the right operand when the left operand is already falsy,
+    /// leaving it as the expression's result, otherwise pops it and evaluates the right
+    /// operand as the result instead.
+    fn and_(&mut self) -> Result<(), LoxResult> {
+        self.advance(); // consume 'and'
+
+        let end_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit(OpCode::Pop);
+        self.parse_precedence(Precedence::And)?;
+        self.patch_jump(end_jump);
+
+        Ok(())
+    }
+
+    /// Compiles the right-hand side of `left or right`: short-circuits by jumping
+    /// straight past the right operand when the left operand is already truthy,
+    /// otherwise pops it and evaluates the right operand as the result instead.
+    fn or_(&mut self) -> Result<(), LoxResult> {
+        self.advance(); // consume 'or'
+
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+        let end_jump = self.emit_jump(OpCode::Jump);
+
+        self.patch_jump(else_jump);
+        self.emit(OpCode::Pop);
+
+        self.parse_precedence(Precedence::Or)?;
+        self.patch_jump(end_jump);
+
+        Ok(())
+    }
+
+    /// Emits `op` followed by a two-byte placeholder offset, returning the index of
+    /// that placeholder so it can later be filled in by `patch_jump`.
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit(op);
+        self.emit_byte(0xff, self.previous_line());
+        self.emit_byte(0xff, self.previous_line());
+        self.chunk.code.len() - 2
+    }
+
+    /// Backpatches the two-byte placeholder at `offset` (as returned by `emit_jump`)
+    /// with the distance from just after the placeholder to the current end of the
+    /// chunk, matching how the VM's `Jump`/`JumpIfFalse` dispatch reads it.
+    fn patch_jump(&mut self, offset: usize) {
+        let jump = self.chunk.code.len() - offset - 2;
+        let bytes = (jump as u16).to_le_bytes();
+        self.chunk.code[offset] = bytes[0];
+        self.chunk.code[offset + 1] = bytes[1];
+    }
+
+    fn number(&mut self) -> Result<(), LoxResult> {
+        let token = self.advance();
+        let line = token.line();
+        let value = token.literal.clone().unwrap();
+        self.emit_constant(value, &token, line)
+    }
+
+    fn string(&mut self) -> Result<(), LoxResult> {
+        let token = self.advance();
+        let line = token.line();
+        let value = token.literal.clone().unwrap();
+        self.emit_constant(value, &token, line)
+    }
+
+    fn literal(&mut self) -> Result<(), LoxResult> {
+        let token = self.advance();
+        let line = token.line();
+
+        match token.ttype {
+            TokenType::False => self.emit_at(OpCode::False, line),
+            TokenType::True => self.emit_at(OpCode::True, line),
+            TokenType::Nil => self.emit_at(OpCode::Nil, line),
+            _ => unreachable!("literal() only called on nil/true/false tokens"),
+        }
+
+        Ok(())
+    }
+
+    fn variable(&mut self) -> Result<(), LoxResult> {
+        let token = self.advance();
+        let line = token.line();
+        let name = Object::str(&token.lexeme);
+
+        let idx = self.chunk.add_constant(name, &token)?;
+
+        if self.matches(TokenType::Equal) {
+            self.expression()?;
+            self.emit_at(OpCode::SetGlobal, line);
+            self.emit_byte(idx, line);
+        } else {
+            self.emit_at(OpCode::GetGlobal, line);
+            self.emit_byte(idx, line);
+        }
+
+        Ok(())
+    }
+
+    fn emit_constant(&mut self, value: Object, token: &Token, line: usize) -> Result<(), LoxResult> {
+        let idx = self.chunk.add_constant(value, token)?;
+        self.emit_at(OpCode::Constant, line);
+        self.emit_byte(idx, line);
+        Ok(())
+    }
+
+    fn rule(ttype: TokenType) -> ParseRule<'a> {
+        match ttype {
+            TokenType::LeftParen => ParseRule {
+                prefix: Some(Compiler::grouping),
+                infix: None,
+                precedence: Precedence::None,
+            },
+            TokenType::Minus => ParseRule {
+                prefix: Some(Compiler::unary),
+                infix: Some(Compiler::binary),
+                precedence: Precedence::Term,
+            },
+            TokenType::Plus => ParseRule {
+                prefix: None,
+                infix: Some(Compiler::binary),
+                precedence: Precedence::Term,
+            },
+            TokenType::Slash | TokenType::Star => ParseRule {
+                prefix: None,
+                infix: Some(Compiler::binary),
+                precedence: Precedence::Factor,
+            },
+            TokenType::Bang => ParseRule {
+                prefix: Some(Compiler::unary),
+                infix: None,
+                precedence: Precedence::None,
+            },
+            TokenType::BangEqual | TokenType::EqualEqual => ParseRule {
+                prefix: None,
+                infix: Some(Compiler::binary),
+                precedence: Precedence::Equality,
+            },
+            TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual => ParseRule {
+                prefix: None,
+                infix: Some(Compiler::binary),
+                precedence: Precedence::Comparison,
+            },
+            TokenType::Number => ParseRule {
+                prefix: Some(Compiler::number),
+                infix: None,
+                precedence: Precedence::None,
+            },
+            TokenType::String => ParseRule {
+                prefix: Some(Compiler::string),
+                infix: None,
+                precedence: Precedence::None,
+            },
+            TokenType::Identifier => ParseRule {
+                prefix: Some(Compiler::variable),
+                infix: None,
+                precedence: Precedence::None,
+            },
+            TokenType::False | TokenType::True | TokenType::Nil => ParseRule {
+                prefix: Some(Compiler::literal),
+                infix: None,
+                precedence: Precedence::None,
+            },
+            TokenType::And => ParseRule {
+                prefix: None,
+                infix: Some(Compiler::and_),
+                precedence: Precedence::And,
+            },
+            TokenType::Or => ParseRule {
+                prefix: None,
+                infix: Some(Compiler::or_),
+                precedence: Precedence::Or,
+            },
+            _ => ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
+        }
+    }
+
+    fn emit(&mut self, op: OpCode) {
+        let line = self.previous_line();
+        self.chunk.write_op(op, line);
+    }
+
+    fn emit_at(&mut self, op: OpCode, line: usize) {
+        self.chunk.write_op(op, line);
+    }
+
+    fn emit_byte(&mut self, byte: u8, line: usize) {
+        self.chunk.write_byte(byte, line);
+    }
+
+    fn previous_line(&self) -> usize {
+        self.tokens[self.current - 1].line()
+    }
+
+    fn matches(&mut self, ttype: TokenType) -> bool {
+        if self.check(ttype) {
+            self.advance();
+            return true;
+        }
+
+        false
+    }
+
+    fn check(&self, ttype: TokenType) -> bool {
+        !self.is_at_end() && self.peek().ttype == ttype
+    }
+
+    fn consume(&mut self, ttype: TokenType, msg: &str) -> Result<Token, LoxResult> {
+        if self.check(ttype) {
+            return Ok(self.advance());
+        }
+
+        Err(LoxResult::Bytecode {
+            token: self.peek().dup(),
+            error_type: BytecodeErrorType::UnexpectedToken,
+            msg: msg.to_string(),
+        })
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.current].dup();
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        token
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().ttype == TokenType::Eof
+    }
+}
+
+/// Stack-based virtual machine that executes a compiled `Chunk`.
+pub struct Vm {
+    stack: Vec<Object>,
+    globals: HashMap<Symbol, Object>,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Vm {
+            stack: Vec::new(),
+            globals: HashMap::new(),
+        }
+    }
+
+    /// Runs every instruction in `chunk` to completion.
+    pub fn run(&mut self, chunk: &Chunk) -> Result<(), LoxResult> {
+        let mut ip = 0;
+
+        while ip < chunk.code.len() {
+            let op = OpCode::try_from(chunk.code[ip]).expect("corrupt chunk: unknown opcode");
+            ip += 1;
+
+            match op {
+                OpCode::Constant => {
+                    let idx = chunk.code[ip] as usize;
+                    ip += 1;
+                    self.stack.push(chunk.constants[idx].clone());
+                }
+                OpCode::Nil => self.stack.push(Object::Nil),
+                OpCode::True => self.stack.push(Object::True),
+                OpCode::False => self.stack.push(Object::False),
+                OpCode::Pop => {
+                    self.stack.pop();
+                }
+                OpCode::DefineGlobal => {
+                    let idx = chunk.code[ip] as usize;
+                    ip += 1;
+                    if let Object::Str(name) = &chunk.constants[idx] {
+                        let value = self.stack.pop().unwrap_or(Object::Nil);
+                        self.globals.insert(*name, value);
+                    }
+                }
+                OpCode::GetGlobal => {
+                    let idx = chunk.code[ip] as usize;
+                    ip += 1;
+                    if let Object::Str(name) = &chunk.constants[idx] {
+                        let value = self.globals.get(name).cloned().unwrap_or(Object::Nil);
+                        self.stack.push(value);
+                    }
+                }
+                OpCode::SetGlobal => {
+                    let idx = chunk.code[ip] as usize;
+                    ip += 1;
+                    if let Object::Str(name) = &chunk.constants[idx] {
+                        let value = self.stack.last().cloned().unwrap_or(Object::Nil);
+                        self.globals.insert(*name, value);
+                    }
+                }
+                OpCode::Equal => {
+                    let b = self.pop();
+                    let a = self.pop();
+                    self.stack.push(Object::from(a == b));
+                }
+                OpCode::Greater => self.binary_number_op(|a, b| Object::from(a > b)),
+                OpCode::Less => self.binary_number_op(|a, b| Object::from(a < b)),
+                OpCode::Add => {
+                    let b = self.pop();
+                    let a = self.pop();
+                    match (a, b) {
+                        (Object::Num(a), Object::Num(b)) => self.stack.push(Object::Num(a + b)),
+                        (Object::Str(a), Object::Str(b)) => {
+                            self.stack.push(Object::str(&(a.resolve() + &b.resolve())));
+                        }
+                        _ => self.stack.push(Object::Nil),
+                    }
+                }
+                OpCode::Subtract => self.binary_number_op(|a, b| Object::Num(a - b)),
+                OpCode::Multiply => self.binary_number_op(|a, b| Object::Num(a * b)),
+                OpCode::Divide => self.binary_number_op(|a, b| Object::Num(a / b)),
+                OpCode::Not => {
+                    let a = self.pop();
+                    self.stack.push(Object::from(!Self::is_truthy(&a)));
+                }
+                OpCode::Negate => {
+                    if let Object::Num(n) = self.pop() {
+                        self.stack.push(Object::Num(-n));
+                    }
+                }
+                OpCode::Print => {
+                    println!("{}", self.pop());
+                }
+                OpCode::Jump => {
+                    let offset = u16::from_le_bytes([chunk.code[ip], chunk.code[ip + 1]]);
+                    ip += 2 + offset as usize;
+                }
+                OpCode::JumpIfFalse => {
+                    let offset = u16::from_le_bytes([chunk.code[ip], chunk.code[ip + 1]]);
+                    ip += 2;
+                    if !Self::is_truthy(self.stack.last().unwrap()) {
+                        ip += offset as usize;
+                    }
+                }
+                OpCode::Loop => {
+                    let offset = u16::from_le_bytes([chunk.code[ip], chunk.code[ip + 1]]);
+                    ip = ip + 2 - offset as usize;
+                }
+                OpCode::Call => {
+                    // Calls are not supported by this first VM pass.
+                }
+                OpCode::Return => return Ok(()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The current value stack, top last -- the `Vm` has no other way to
+    /// observe what an expression evaluated to, so tests read it directly.
+    pub fn stack(&self) -> &[Object] {
+        &self.stack
+    }
+
+    /// Looks up a global by name, for tests/debugging -- mirrors
+    /// `Environment::get` for the tree-walking interpreter's globals.
+    pub fn global(&self, name: &str) -> Option<Object> {
+        self.globals.get(&Symbol::intern(name)).cloned()
+    }
+
+    fn pop(&mut self) -> Object {
+        self.stack.pop().unwrap_or(Object::Nil)
+    }
+
+    fn binary_number_op(&mut self, op: impl Fn(f64, f64) -> Object) {
+        let b = self.pop();
+        let a = self.pop();
+
+        if let (Object::Num(a), Object::Num(b)) = (a, b) {
+            self.stack.push(op(a, b));
+        } else {
+            self.stack.push(Object::Nil);
+        }
+    }
+
+    fn is_truthy(obj: &Object) -> bool {
+        !matches!(obj, Object::Nil | Object::False)
+    }
+}
+
+impl From<bool> for Object {
+    fn from(b: bool) -> Self {
+        if b {
+            Object::True
+        } else {
+            Object::False
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gcmodule::{Cc, Trace, Tracer};
+
+use crate::environment::Environment;
+use crate::errors::LoxResult;
+use crate::interpreter::Interpreter;
+use crate::stmt::Stmt;
+use crate::token::{Object, Token};
+
+/// A native function exposed to Lox programs. Implementors are `'static` so
+/// they can live behind a plain `&'static dyn Builtin` inside `Callable`
+/// instead of needing their own heap allocation per call.
+pub trait Builtin: fmt::Debug {
+    fn name(&self) -> &str;
+    fn arity(&self) -> usize;
+    fn call(&self, interpreter: &mut Interpreter, arguments: Vec<Object>) -> Result<Object, LoxResult>;
+}
+
+/// Anything `Object::Callable` can wrap: a native `Builtin` or a
+/// user-defined `LoxFunction`.
+#[derive(Debug, Clone)]
+pub enum Callable {
+    Builtin(&'static dyn Builtin),
+    Function(Rc<LoxFunction>),
+}
+
+impl Callable {
+    pub fn name(&self) -> &str {
+        match self {
+            Callable::Builtin(builtin) => builtin.name(),
+            Callable::Function(function) => &function.name.lexeme,
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        match self {
+            Callable::Builtin(builtin) => builtin.arity(),
+            Callable::Function(function) => function.arity(),
+        }
+    }
+
+    pub fn call(&self, interpreter: &mut Interpreter, arguments: Vec<Object>) -> Result<Object, LoxResult> {
+        match self {
+            Callable::Builtin(builtin) => builtin.call(interpreter, arguments),
+            Callable::Function(function) => function.call(interpreter, arguments),
+        }
+    }
+}
+
+impl PartialEq for Callable {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Callable::Builtin(a), Callable::Builtin(b)) => {
+                std::ptr::eq(*a as *const dyn Builtin as *const (), *b as *const dyn Builtin as *const ())
+            }
+            (Callable::Function(a), Callable::Function(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// Builtins hold no state to trace; a `Function` reaches into its closure,
+/// which is where a capture cycle would actually live.
+impl Trace for Callable {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Callable::Function(function) = self {
+            // `Rc<LoxFunction>` itself isn't `Trace`, so trace through it by
+            // hand rather than via the `Rc`.
+            (**function).trace(tracer);
+        }
+    }
+}
+
+/// A user-defined function: its declaration plus the environment it closed
+/// over at the point it was declared.
+#[derive(Debug)]
+pub struct LoxFunction {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+    pub closure: Cc<RefCell<Environment>>,
+}
+
+impl Trace for LoxFunction {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.closure.trace(tracer);
+    }
+}
+
+impl LoxFunction {
+    pub fn new(name: Token, params: Vec<Token>, body: Vec<Stmt>, closure: Cc<RefCell<Environment>>) -> Self {
+        LoxFunction {
+            name,
+            params,
+            body,
+            closure,
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Runs the function body in a fresh activation record enclosed by the
+    /// closure it was declared in, with each parameter bound to its matching
+    /// argument. A `return` inside the body surfaces here as
+    /// `LoxResult::ReturnValue` rather than propagating further; falling off
+    /// the end yields `Nil`.
+    pub fn call(&self, interpreter: &mut Interpreter, arguments: Vec<Object>) -> Result<Object, LoxResult> {
+        let call_env = Cc::new(RefCell::new(Environment::from_enclosing_with_capacity(
+            self.closure.clone(),
+            self.params.len(),
+        )));
+
+        for (param, argument) in self.params.iter().zip(arguments) {
+            call_env.borrow_mut().define(&param.lexeme, argument);
+        }
+
+        match interpreter.execute_block(&self.body, call_env) {
+            Ok(()) => Ok(Object::Nil),
+            Err(LoxResult::ReturnValue { value }) => Ok(value),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// `clock()` — seconds since the Unix epoch, as a `Num`. The only native
+/// function registered so far; more builtins can be added alongside it.
+#[derive(Debug)]
+pub struct ClockBuiltin;
+
+pub static CLOCK: ClockBuiltin = ClockBuiltin;
+
+/// Looks up a native function by name without registering it anywhere --
+/// used by the global `Environment` to materialize a builtin the first time
+/// a program actually names it.
+pub fn lookup_builtin(name: &str) -> Option<&'static dyn Builtin> {
+    match name {
+        "clock" => Some(&CLOCK),
+        _ => None,
+    }
+}
+
+impl Builtin for ClockBuiltin {
+    fn name(&self) -> &str {
+        "clock"
+    }
+
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, _interpreter: &mut Interpreter, _arguments: Vec<Object>) -> Result<Object, LoxResult> {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        Ok(Object::Num(seconds))
+    }
+}
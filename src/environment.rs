@@ -1,17 +1,155 @@
 use std::cell::RefCell;
-use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt;
-use std::rc::Rc;
 
+use gcmodule::{Cc, Trace, Tracer};
+
+use crate::callable::{lookup_builtin, Callable};
 use crate::errors::{EnvironmentErrorType, LoxResult};
+use crate::interner::Symbol;
 use crate::object::Object;
 use crate::token::Token;
 
+/// Past this many bindings a scope is promoted from a linear `Vec` scan to a
+/// `HashMap`: below it the `Vec`'s better constant factor (no hashing, no
+/// pointer chasing) beats a hash lookup; above it, hashing wins.
+const PROMOTE_THRESHOLD: usize = 8;
+
+/// Name-keyed storage for a scope's bindings. Starts as a flat `Vec` --
+/// cheap for the handful of locals most scopes hold -- and promotes itself
+/// to a `HashMap` once it grows past `PROMOTE_THRESHOLD` entries. `define`,
+/// `get` and `assign` read and write through this transparently; callers
+/// never need to know which representation backs a given scope.
+#[derive(Debug)]
+enum Bindings {
+    Small(Vec<(Symbol, Object)>),
+    Large(HashMap<Symbol, Object>),
+}
+
+impl Bindings {
+    fn with_capacity(capacity: usize) -> Self {
+        if capacity > PROMOTE_THRESHOLD {
+            Bindings::Large(HashMap::with_capacity(capacity))
+        } else {
+            Bindings::Small(Vec::with_capacity(capacity))
+        }
+    }
+
+    fn insert(&mut self, key: Symbol, value: Object) {
+        if let Bindings::Small(entries) = self {
+            if let Some(entry) = entries.iter_mut().find(|(k, _)| *k == key) {
+                entry.1 = value;
+                return;
+            }
+
+            if entries.len() == PROMOTE_THRESHOLD {
+                let mut map: HashMap<Symbol, Object> = entries.drain(..).collect();
+                map.insert(key, value);
+                *self = Bindings::Large(map);
+                return;
+            }
+
+            entries.push((key, value));
+            return;
+        }
+
+        if let Bindings::Large(map) = self {
+            map.insert(key, value);
+        }
+    }
+
+    fn get(&self, key: Symbol) -> Option<&Object> {
+        match self {
+            Bindings::Small(entries) => entries.iter().find(|(k, _)| *k == key).map(|(_, v)| v),
+            Bindings::Large(map) => map.get(&key),
+        }
+    }
+
+    fn get_mut(&mut self, key: Symbol) -> Option<&mut Object> {
+        match self {
+            Bindings::Small(entries) => entries.iter_mut().find(|(k, _)| *k == key).map(|(_, v)| v),
+            Bindings::Large(map) => map.get_mut(&key),
+        }
+    }
+
+    fn contains_key(&self, key: Symbol) -> bool {
+        self.get(key).is_some()
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Bindings::Small(entries) => entries.len(),
+            Bindings::Large(map) => map.len(),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Symbol, &Object)> + '_> {
+        match self {
+            Bindings::Small(entries) => Box::new(entries.iter().map(|(k, v)| (*k, v))),
+            Bindings::Large(map) => Box::new(map.iter().map(|(k, v)| (*k, v))),
+        }
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings::Small(Vec::new())
+    }
+}
+
+impl Trace for Bindings {
+    fn trace(&self, tracer: &mut Tracer) {
+        match self {
+            Bindings::Small(entries) => {
+                for (_, value) in entries {
+                    value.trace(tracer);
+                }
+            }
+            Bindings::Large(map) => {
+                for value in map.values() {
+                    value.trace(tracer);
+                }
+            }
+        }
+    }
+}
+
+/// The operations every kind of lexical scope supports, regardless of what
+/// extra state it carries. Mirrors the ECMAScript "environment record"
+/// split (declarative / global records behind one interface) so the
+/// interpreter can dispatch through it without caring whether it's talking
+/// to the global scope or an ordinary block.
+pub trait EnvironmentRecord {
+    fn get_binding(&mut self, name: &Token) -> Result<Object, LoxResult>;
+    fn set_mutable_binding(&mut self, name: &Token, value: Object) -> Result<(), LoxResult>;
+}
+
+/// What extra state an `Environment` carries beyond its bindings, i.e. which
+/// environment record it plays the role of.
+#[derive(Debug, Default)]
+enum EnvironmentKind {
+    /// An ordinary block scope: `{ ... }`, a loop body, a function's
+    /// activation record, and so on.
+    #[default]
+    Declarative,
+    /// The outermost scope. Native functions are registered here lazily,
+    /// the first time they're looked up, rather than all at startup --
+    /// `clock` never occupies the map unless a program actually names it.
+    Global,
+}
+
 #[derive(Debug)]
 pub struct Environment {
-    pub enclosing: Option<Rc<RefCell<Environment>>>,
-    values: HashMap<String, Object>,
+    pub enclosing: Option<Cc<RefCell<Environment>>>,
+    values: Bindings,
+    /// Locals in declaration order, indexed by the slot the `Resolver`
+    /// assigned them. Lets a resolved access skip `values` entirely and go
+    /// straight to `slots[slot]` with no hashing. Populated alongside
+    /// `values` by every `define` call; only resolved (local) accesses ever
+    /// read from it -- dynamic/unresolved lookups (`get`/`assign`, and every
+    /// global) still go through `values`.
+    slots: Vec<Object>,
+    kind: EnvironmentKind,
 }
 
 impl Default for Environment {
@@ -20,10 +158,24 @@ impl Default for Environment {
     }
 }
 
+/// Lets the cycle collector walk past an `Environment` into both the scope it
+/// encloses and every value it holds, so a closure that captures the very
+/// environment holding it (a recursive local function, two mutually
+/// recursive locals, ...) still gets collected instead of leaking forever.
+impl Trace for Environment {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.enclosing.trace(tracer);
+        self.values.trace(tracer);
+        for value in &self.slots {
+            value.trace(tracer);
+        }
+    }
+}
+
 impl fmt::Display for Environment {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for (k, v) in &self.values {
-            writeln!(f, "{} = {}", k, v)?
+        for (k, v) in self.values.iter() {
+            writeln!(f, "{} = {}", k.resolve(), v)?
         }
 
         if self.enclosing.is_some() {
@@ -41,26 +193,65 @@ impl fmt::Display for Environment {
 }
 
 impl Environment {
-    /// Useless
+    /// Builds the global scope: the one `Environment` with no `enclosing`
+    /// and with `EnvironmentKind::Global`, so native functions get
+    /// materialized lazily instead of up front.
     pub fn new() -> Self {
         Environment {
             enclosing: None,
-            values: HashMap::new(),
+            values: Bindings::default(),
+            slots: Vec::new(),
+            kind: EnvironmentKind::Global,
         }
     }
 
-    pub fn from_enclosing(env: Rc<RefCell<Environment>>) -> Self {
+    /// Builds an ordinary block scope enclosed by `env` (a `{ ... }`, a loop
+    /// body, and so on).
+    pub fn from_enclosing(env: Cc<RefCell<Environment>>) -> Self {
+        Self::from_enclosing_kind(env, EnvironmentKind::Declarative, 0)
+    }
+
+    /// Builds an ordinary block scope enclosed by `env`, pre-sized for
+    /// `capacity` bindings -- lets the interpreter size a function's
+    /// activation record from its declared parameter/local count up front
+    /// instead of growing it one `define` at a time.
+    pub fn from_enclosing_with_capacity(env: Cc<RefCell<Environment>>, capacity: usize) -> Self {
+        Self::from_enclosing_kind(env, EnvironmentKind::Declarative, capacity)
+    }
+
+    fn from_enclosing_kind(env: Cc<RefCell<Environment>>, kind: EnvironmentKind, capacity: usize) -> Self {
         Environment {
             enclosing: Some(env),
-            values: HashMap::new(),
+            values: Bindings::with_capacity(capacity),
+            slots: Vec::with_capacity(capacity),
+            kind,
         }
     }
 
     /**
-     * Inserts a key-value pair in the global HashMap storage.
+     * Inserts a key-value pair in the name-keyed storage, and appends the
+     * value to the slot vector at the index the `Resolver` will have
+     * precomputed for it (declaration order).
      */
-    pub fn define(&mut self, name: String, obj: Object) {
-        self.values.insert(name, obj);
+    pub fn define(&mut self, name: &str, obj: Object) {
+        self.values.insert(Symbol::intern(name), obj.clone());
+        self.slots.push(obj);
+    }
+
+    /// Registers `name` as a native function the first time it's looked up,
+    /// if this is the global scope and nothing has defined it already.
+    fn materialize_builtin(&mut self, name: &str) {
+        if !matches!(self.kind, EnvironmentKind::Global) {
+            return;
+        }
+
+        if self.values.contains_key(Symbol::intern(name)) {
+            return;
+        }
+
+        if let Some(builtin) = lookup_builtin(name) {
+            self.define(name, Object::Callable(Callable::Builtin(builtin)));
+        }
     }
 
     /**
@@ -70,7 +261,7 @@ impl Environment {
      */
     pub fn get(&self, token: &Token) -> Result<Object, LoxResult> {
         // Check if the variable exists locally
-        if let Some(v) = self.values.get(&token.lexeme) {
+        if let Some(v) = self.values.get(Symbol::intern(&token.lexeme)) {
             return Ok(v.clone());
         }
 
@@ -91,33 +282,26 @@ impl Environment {
     }
 
     /**
-     * Gets a variable from the environment that is at depth `distance`.
+     * Gets the value at `slot` in the environment `distance` scopes up, as
+     * precomputed by the `Resolver`. No hashing, no fallible lookup: a
+     * resolved slot is guaranteed to exist.
      */
-    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Object, LoxResult> {
-        // If given a distance, get the corresponding ancestor and try to get the value from it
+    pub fn get_at(&self, distance: usize, slot: usize) -> Object {
         if distance > 0 {
-            self.ancestor(distance).borrow().get(name)
-        }
-        // If no distance, only try to get the value from self.values or return an error
-        else {
-            match self.values.get(&name.lexeme) {
-                Some(val) => Ok(val.clone()),
-                None => Err(LoxResult::Environment {
-                    error_type: EnvironmentErrorType::UnknownVariable,
-                    msg: format!("{} -> No such variable '{}'.", name.location(), name.lexeme),
-                }),
-            }
+            self.ancestor(distance).borrow().slots[slot].clone()
+        } else {
+            self.slots[slot].clone()
         }
     }
 
     /**
      * Gets the enclosed environment at depth `distance`.
      */
-    fn ancestor(&self, distance: usize) -> Rc<RefCell<Environment>> {
+    fn ancestor(&self, distance: usize) -> Cc<RefCell<Environment>> {
         // Get the first enclosing env or panic
         let parent = self.enclosing.clone().expect("No ancestor at depth 1.");
         // Get a reference of that env
-        let mut env = Rc::clone(&parent);
+        let mut env = parent.clone();
         // Get the parent env distance times
         for i in 1..distance {
             // Get the parent or panic
@@ -127,7 +311,7 @@ impl Environment {
                 .clone()
                 .unwrap_or_else(|| panic!("No ancestor at depth {i}."));
             // Get the parent as a reference
-            env = Rc::clone(&parent);
+            env = parent.clone();
         }
 
         // Return the env
@@ -136,8 +320,8 @@ impl Environment {
 
     pub fn assign(&mut self, token: &Token, value: Object) -> Result<(), LoxResult> {
         // Try inserting in the local variables
-        if let Entry::Occupied(mut e) = self.values.entry(token.lexeme.clone()) {
-            e.insert(value);
+        if let Some(slot) = self.values.get_mut(Symbol::intern(&token.lexeme)) {
+            *slot = value;
             return Ok(());
         }
 
@@ -157,12 +341,72 @@ impl Environment {
         })
     }
 
-    pub fn assign_at(
-        &mut self,
-        distance: usize,
-        name: &Token,
-        value: Object,
-    ) -> Result<(), LoxResult> {
-        self.ancestor(distance).borrow_mut().assign(name, value)
+    /**
+     * Assigns the value at `slot` in the environment `distance` scopes up, as
+     * precomputed by the `Resolver`.
+     */
+    pub fn assign_at(&mut self, distance: usize, slot: usize, value: Object) {
+        if distance > 0 {
+            self.ancestor(distance).borrow_mut().slots[slot] = value;
+        } else {
+            self.slots[slot] = value;
+        }
+    }
+
+    /// Number of bindings in this frame alone, not counting any enclosing
+    /// scope.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether this frame alone holds any bindings.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Names bound in this frame alone, in no particular order.
+    pub fn names(&self) -> Vec<String> {
+        self.values.iter().map(|(k, _)| k.resolve()).collect()
+    }
+
+    /// Whether `name` is bound in this frame alone (not counting any
+    /// enclosing scope).
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(Symbol::intern(name))
+    }
+
+    /// Walks the `enclosing` chain and returns the effective set of visible
+    /// bindings, with an inner scope's binding shadowing an outer one of the
+    /// same name. Useful for a REPL `:env` command or a debugger's "locals"
+    /// view, where a user wants everything currently in scope at a glance.
+    pub fn flatten_visible(&self) -> HashMap<String, Object> {
+        let mut visible = match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().flatten_visible(),
+            None => HashMap::new(),
+        };
+
+        for (k, v) in self.values.iter() {
+            visible.insert(k.resolve(), v.clone());
+        }
+
+        visible
+    }
+
+    /// Clones this frame's bindings alone, for diffing against a later
+    /// snapshot -- e.g. to show what changed before/after a stepped
+    /// statement in a debugger.
+    pub fn snapshot(&self) -> HashMap<String, Object> {
+        self.values.iter().map(|(k, v)| (k.resolve(), v.clone())).collect()
+    }
+}
+
+impl EnvironmentRecord for Environment {
+    fn get_binding(&mut self, name: &Token) -> Result<Object, LoxResult> {
+        self.materialize_builtin(&name.lexeme);
+        self.get(name)
+    }
+
+    fn set_mutable_binding(&mut self, name: &Token, value: Object) -> Result<(), LoxResult> {
+        self.assign(name, value)
     }
 }
@@ -3,6 +3,27 @@ use std::fmt;
 use crate::object::Object;
 use crate::token::Token;
 
+/// A single parser diagnostic, carrying the offending token (for its lexeme,
+/// line, and type) alongside a human-readable message. The `Parser`
+/// accumulates these across a whole pass instead of aborting on the first.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub token: Token,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}] Error at '{}': {}",
+            self.token.line(),
+            self.token.lexeme,
+            self.message
+        )
+    }
+}
+
 #[derive(Debug)]
 pub enum RuntimeErrorType {
     UnreachableCode,
@@ -13,10 +34,30 @@ pub enum RuntimeErrorType {
     InvalidArgsCount,
 }
 
+impl RuntimeErrorType {
+    /// The human-readable message for this error, independent of any
+    /// location prefix -- shared by `Display` and `LoxResult::report_runtime`
+    /// so the two don't drift apart.
+    fn message(&self) -> &'static str {
+        match self {
+            RuntimeErrorType::UnreachableCode => "This code is unreachable.",
+            RuntimeErrorType::ExpectedNumberOperand => "Operand must be a number.",
+            RuntimeErrorType::ExpectedNumberOperands => "Both operands must be a number.",
+            RuntimeErrorType::ExpectedAddableOperands => {
+                "Operands must be two numbers or two strings."
+            }
+            RuntimeErrorType::InvalidCallObjectType => "Can only call functions and classes.",
+            RuntimeErrorType::InvalidArgsCount => "Invalid argument count for function or class.",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ScannerErrorType {
     InvalidCharacter,
     UnterminatedString,
+    InvalidEscape,
+    UnterminatedComment,
 }
 
 #[derive(Debug)]
@@ -25,6 +66,8 @@ pub enum ParserErrorType {
     InvalidConsumeType,
     InvalidAssignTarget,
     MaxArgNumber,
+    LoopControlOutsideLoop,
+    MissingSemicolon,
 }
 
 #[derive(Debug)]
@@ -39,6 +82,13 @@ pub enum EnvironmentErrorType {
     UnknownVariable,
 }
 
+#[derive(Debug)]
+pub enum BytecodeErrorType {
+    UnexpectedToken,
+    TooManyConstants,
+    JumpTooFar,
+}
+
 #[derive(Debug)]
 pub enum LoxResult {
     Parser {
@@ -53,6 +103,9 @@ pub enum LoxResult {
     Scanner {
         c: char,
         error_type: ScannerErrorType,
+        line: usize,
+        column: usize,
+        source_line: String,
     },
     Environment {
         error_type: EnvironmentErrorType,
@@ -61,33 +114,91 @@ pub enum LoxResult {
     ReturnValue {
         value: Object,
     },
+    /// Unwinds execution back to the nearest enclosing loop, the same way
+    /// `ReturnValue` unwinds back to the nearest enclosing call.
+    Break,
+    Continue,
     Resolver {
         token: Token,
         error_type: ResolverErrorType,
     },
+    Bytecode {
+        token: Token,
+        error_type: BytecodeErrorType,
+        msg: String,
+    },
 }
 
 impl LoxResult {
-    /*
-    pub fn error() -> Self{
-        report(line, "".to_string(), msg);
+    /// Renders a "[line N] Error: msg" header followed by the offending
+    /// source line and a `^` caret pointing at `column`.
+    fn report(line: usize, column: usize, source_line: &str, msg: &str) -> String {
+        let caret = format!("{}^", " ".repeat(column));
+        format!("[line {line}] Error: {msg}\n{source_line}\n{caret}")
     }
 
-    pub fn report(line: usize, location: String, msg: String) -> Self{
-        eprintln!("[line {}] Error {}: {}", line, location, msg);
+    /// Like `report`, but for a `Runtime` error: underlines the offending
+    /// token's whole span (`^~~~` rather than a single `^`) against `source`,
+    /// the original program text. Returns `None` for every other variant.
+    pub fn report_runtime(&self, source: &str) -> Option<String> {
+        let LoxResult::Runtime { token, error_type } = self else {
+            return None;
+        };
+
+        let (line, start, end) = token.span();
+        let (source_line, line_start) = Self::line_at(source, line);
+        let column = start.saturating_sub(line_start);
+        let width = end.saturating_sub(start).max(1);
+        let underline = format!("{}^{}", " ".repeat(column), "~".repeat(width - 1));
+
+        Some(format!(
+            "[line {line}] Error: {}\n{source_line}\n{underline}",
+            error_type.message()
+        ))
+    }
+
+    /// Returns the text of `source`'s 1-indexed `line`, plus the char offset
+    /// its first character sits at within `source`.
+    fn line_at(source: &str, line: usize) -> (&str, usize) {
+        let mut offset = 0;
+
+        for (idx, text) in source.split('\n').enumerate() {
+            if idx + 1 == line {
+                return (text, offset);
+            }
+
+            offset += text.chars().count() + 1;
+        }
+
+        ("", offset)
     }
-    */
 }
 
 impl fmt::Display for LoxResult {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            LoxResult::Scanner { c, error_type } => match error_type {
-                ScannerErrorType::InvalidCharacter => write!(f, "Invalid character {c}.")?,
-                ScannerErrorType::UnterminatedString => {
-                    write!(f, "Encountered an unterminated string.")?
-                }
-            },
+            LoxResult::Scanner {
+                c,
+                error_type,
+                line,
+                column,
+                source_line,
+            } => {
+                let msg = match error_type {
+                    ScannerErrorType::InvalidCharacter => format!("Invalid character {c}."),
+                    ScannerErrorType::UnterminatedString => {
+                        "Encountered an unterminated string.".to_string()
+                    }
+                    ScannerErrorType::InvalidEscape => {
+                        format!("Invalid escape sequence '\\{c}'.")
+                    }
+                    ScannerErrorType::UnterminatedComment => {
+                        "Encountered an unterminated block comment.".to_string()
+                    }
+                };
+
+                write!(f, "{}", Self::report(*line, *column, source_line, &msg))?
+            }
 
             // Parser error
             LoxResult::Parser {
@@ -105,30 +216,22 @@ impl fmt::Display for LoxResult {
                     "{} -> Cannot have more than 255 arguments.",
                     token.location()
                 )?,
-            },
-
-            // Runtime error
-            LoxResult::Runtime { token, error_type } => match error_type {
-                RuntimeErrorType::UnreachableCode => {
-                    writeln!(f, "This code is unreachable.")?;
-                }
-                RuntimeErrorType::ExpectedNumberOperand => write!(f, "Operand must be a number.")?,
-                RuntimeErrorType::ExpectedNumberOperands => {
-                    write!(f, "Both operands must be a number.")?
-                }
-                RuntimeErrorType::InvalidCallObjectType => write!(
+                ParserErrorType::LoopControlOutsideLoop => write!(
                     f,
-                    "{} -> Can only call functions and classes.",
-                    token.location()
+                    "{} -> Can't use '{}' outside of a loop.",
+                    token.location(),
+                    token.lexeme
                 )?,
-                RuntimeErrorType::ExpectedAddableOperands => {
-                    write!(f, "Operands must be two numbers or two strings.")?
-                }
-                RuntimeErrorType::InvalidArgsCount => {
-                    write!(f, "Invalid argument count for function or class.")?
+                ParserErrorType::MissingSemicolon => {
+                    write!(f, "{} -> Missing ';' here. {msg}", token.location())?
                 }
             },
 
+            // Runtime error
+            LoxResult::Runtime { token, error_type } => {
+                write!(f, "{} -> {}", token.location(), error_type.message())?
+            }
+
             // Environment errors
             LoxResult::Environment { error_type, msg } => match error_type {
                 EnvironmentErrorType::UnknownVariable => write!(f, "{msg}")?,
@@ -137,6 +240,10 @@ impl fmt::Display for LoxResult {
             // Return value
             LoxResult::ReturnValue { value } => write!(f, "return {value}")?,
 
+            // Loop control signals
+            LoxResult::Break => write!(f, "break")?,
+            LoxResult::Continue => write!(f, "continue")?,
+
             // Resolver Error
             LoxResult::Resolver { token, error_type } => match error_type {
                 ResolverErrorType::VariableNotInitialized => write!(
@@ -156,6 +263,25 @@ impl fmt::Display for LoxResult {
                     token.location()
                 )?,
             },
+
+            // Bytecode compiler errors
+            LoxResult::Bytecode {
+                token,
+                error_type,
+                msg,
+            } => match error_type {
+                BytecodeErrorType::UnexpectedToken => {
+                    write!(f, "{} -> {msg}", token.location())?
+                }
+                BytecodeErrorType::TooManyConstants => write!(
+                    f,
+                    "{} -> Too many constants in one chunk.",
+                    token.location()
+                )?,
+                BytecodeErrorType::JumpTooFar => {
+                    write!(f, "{} -> Jump distance too far to encode.", token.location())?
+                }
+            },
         }
 
         Ok(())
@@ -0,0 +1,59 @@
+use crate::object::Object;
+use crate::token::Token;
+
+/**
+ * An expression produced by the parser and walked by the interpreter.
+ */
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Assign {
+        name: Token,
+        value: Box<Expr>,
+        /// Number of scopes between this assignment and the scope that
+        /// declares `name`, as computed by the `Resolver`. `None` means the
+        /// variable is global and should be looked up by name instead.
+        depth: Option<usize>,
+        /// Position of `name` within its declaring scope's slot vector, as
+        /// computed by the `Resolver`. `Some` exactly when `depth` is, so the
+        /// interpreter can jump straight to `ancestor(depth).slots[slot]`
+        /// instead of hashing `name`.
+        slot: Option<usize>,
+    },
+    Binary {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        paren: Token,
+        arguments: Vec<Expr>,
+    },
+    Grouping {
+        expression: Box<Expr>,
+    },
+    Literal {
+        value: Option<Object>,
+    },
+    Logical {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Unary {
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Variable {
+        name: Token,
+        /// Number of scopes between this read and the scope that declares
+        /// `name`, as computed by the `Resolver`. `None` means the variable
+        /// is global and should be looked up by name instead.
+        depth: Option<usize>,
+        /// Position of `name` within its declaring scope's slot vector, as
+        /// computed by the `Resolver`. `Some` exactly when `depth` is, so the
+        /// interpreter can jump straight to `ancestor(depth).slots[slot]`
+        /// instead of hashing `name`.
+        slot: Option<usize>,
+    },
+}
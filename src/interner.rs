@@ -0,0 +1,59 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+thread_local! {
+    /// Backs every `Object::Str` and variable/global name, so identical
+    /// literals and identifiers share one allocation and compare by integer
+    /// id instead of by content.
+    static INTERNER: RefCell<StrInterner> = RefCell::new(StrInterner::new());
+}
+
+/// A small integer handle for a string that has been deduplicated through
+/// the interner. Two symbols compare equal, in O(1), iff they were interned
+/// from identical text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Interns `s`, returning the existing handle if this text has already
+    /// been seen.
+    pub fn intern(s: &str) -> Symbol {
+        INTERNER.with(|interner| interner.borrow_mut().intern(s))
+    }
+
+    /// Returns the original text this symbol was interned from.
+    pub fn resolve(self) -> String {
+        INTERNER.with(|interner| interner.borrow().resolve(self).to_string())
+    }
+}
+
+/// Deduplicates strings behind a dense `Vec<Rc<str>>`: repeated identical
+/// text interns to the same `Symbol`, and `resolve` is an O(1) index.
+#[derive(Debug, Default)]
+pub struct StrInterner {
+    strings: Vec<Rc<str>>,
+    ids: HashMap<Rc<str>, u32>,
+}
+
+impl StrInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(&id) = self.ids.get(s) {
+            return Symbol(id);
+        }
+
+        let rc: Rc<str> = Rc::from(s);
+        let id = self.strings.len() as u32;
+        self.strings.push(Rc::clone(&rc));
+        self.ids.insert(rc, id);
+        Symbol(id)
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.0 as usize]
+    }
+}
@@ -1,229 +1,376 @@
-use crate::environment::Environment;
-use crate::errors::ExprError;
-use crate::errors::StmtError;
-use crate::expr::*;
-use crate::stmt::*;
-use crate::token::Object;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gcmodule::Cc;
+
+use crate::callable::{Callable, LoxFunction};
+use crate::environment::{Environment, EnvironmentRecord};
+use crate::errors::{LoxResult, RuntimeErrorType};
+use crate::expr::Expr;
+use crate::stmt::Stmt;
+use crate::token::{Object, Token};
 use crate::token_type::TokenType;
 
+/// Tree-walking evaluator for the statements/expressions produced by the
+/// `Parser`. Holds the current lexical environment plus a separate handle to
+/// the global one, so native functions and top-level declarations stay
+/// reachable no matter how deeply nested the call stack gets.
 pub struct Interpreter {
-    pub environment: Environment,
+    pub globals: Cc<RefCell<Environment>>,
+    pub environment: Cc<RefCell<Environment>>,
 }
 
-impl ExprVisitor<Object> for Interpreter {
-    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<Object, ExprError> {
-        Ok(expr.value.clone().unwrap())
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<Object, ExprError> {
-        let right = self.evaluate(&expr.right)?;
+impl Interpreter {
+    pub fn new() -> Self {
+        let globals = Cc::new(RefCell::new(Environment::new()));
 
-        match expr.operator.ttype {
-            TokenType::Minus => {
-                if let Object::Num(x) = right {
-                    Ok(Object::Num(-x))
-                } else {
-                    Err(ExprError::ExpectedNumberOperand)
+        Interpreter {
+            globals: globals.clone(),
+            environment: globals,
+        }
+    }
+
+    /// Runs `statements`, printing any runtime error as a caret-underlined
+    /// diagnostic against `source` (the original program text) rather than
+    /// the plain `Display` message.
+    pub fn interpret(&mut self, statements: &[Stmt], source: &str) {
+        for statement in statements {
+            if let Err(e) = self.execute(statement) {
+                match e.report_runtime(source) {
+                    Some(report) => eprintln!("{report}"),
+                    None => eprintln!("{}", e),
                 }
             }
-            TokenType::Bang => Ok(Object::from(!self.is_truthy(right))),
-            _ => Err(ExprError::UnreachableCode),
+
+            Self::collect();
         }
     }
 
-    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<Object, ExprError> {
-        self.evaluate(&expr.expression)
+    /// Runs a collection pass over every `Cc`-tracked `Environment` still
+    /// reachable only through reference cycles (e.g. a closure that captures
+    /// the scope holding it), freeing them. Called between top-level
+    /// statements since that's the only point guaranteed to sit outside any
+    /// in-progress borrow of an `Environment`.
+    pub fn collect() {
+        gcmodule::collect_thread_cycles();
     }
 
-    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<Object, ExprError> {
-        let left = self.evaluate(&expr.left)?;
-        let right = self.evaluate(&expr.right)?;
-
-        match expr.operator.ttype {
-            TokenType::Minus => {
-                if let Object::Num(left) = left {
-                    if let Object::Num(right) = right {
-                        return Ok(Object::from(left - right));
-                    }
-                }
-
-                // TODO: Specific error
-                Err(ExprError::InvalidExpression)
+    pub fn execute(&mut self, stmt: &Stmt) -> Result<(), LoxResult> {
+        match stmt {
+            Stmt::Expression { expression } => {
+                self.evaluate(expression)?;
+                Ok(())
             }
 
-            TokenType::Slash => {
-                if let Object::Num(left) = left {
-                    if let Object::Num(right) = right {
-                        return Ok(Object::from(left / right));
-                    }
-                }
-
-                // TODO: Specific error
-                Err(ExprError::InvalidExpression)
+            Stmt::Print { expression } => {
+                let value = self.evaluate(expression)?;
+                println!("{}", value);
+                Ok(())
             }
 
-            // Handle number multiplication
-            TokenType::Star => {
-                if let Object::Num(left) = left {
-                    if let Object::Num(right) = right {
-                        return Ok(Object::from(left * right));
-                    }
-                }
+            Stmt::Var { name, initializer } => {
+                let value = match initializer {
+                    Some(init) => self.evaluate(init)?,
+                    None => Object::Nil,
+                };
 
-                // TODO: Specific error
-                Err(ExprError::InvalidExpression)
+                self.environment.borrow_mut().define(&name.lexeme, value);
+                Ok(())
             }
 
-            // Handle addition (number or string)
-            TokenType::Plus => {
-                // Handle 2 numbers
-                if let Object::Num(left) = left {
-                    if let Object::Num(right) = right {
-                        return Ok(Object::from(left + right));
-                    }
-                }
+            Stmt::Block { statements } => {
+                let block_env = Cc::new(RefCell::new(Environment::from_enclosing(self.environment.clone())));
+                self.execute_block(statements, block_env)
+            }
 
-                // Handle 2 strings
-                if let Object::Str(left) = left {
-                    if let Object::Str(right) = right {
-                        let mut s = left;
-                        s.push_str(&right);
-                        return Ok(Object::from(s));
-                    }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let condition = self.evaluate(condition)?;
+                if self.is_truthy(&condition) {
+                    self.execute(then_branch)
+                } else if let Some(else_branch) = else_branch.as_ref() {
+                    self.execute(else_branch)
+                } else {
+                    Ok(())
                 }
-
-                // TODO: Specific error for when 2 different type (a string and a number)
-                // TODO: Specific error
-                Err(ExprError::ExpectedAddableOperands)
             }
 
-            // Comparison operators
-            //Handle '>'
-            TokenType::Greater => {
-                if let Object::Num(left) = left {
-                    if let Object::Num(right) = right {
-                        return Ok(Object::from(left > right));
+            Stmt::While {
+                condition,
+                body,
+                increment,
+            } => {
+                loop {
+                    let condition_value = self.evaluate(condition)?;
+                    if !self.is_truthy(&condition_value) {
+                        break;
                     }
-                }
 
-                // TODO: Specific error
-                Err(ExprError::ExpectedNumberOperands)
-            }
+                    match self.execute(body) {
+                        Ok(()) | Err(LoxResult::Continue) => {}
+                        Err(LoxResult::Break) => break,
+                        Err(e) => return Err(e),
+                    }
 
-            //Handle '>='
-            TokenType::GreaterEqual => {
-                if let Object::Num(left) = left {
-                    if let Object::Num(right) = right {
-                        return Ok(Object::from(left >= right));
+                    if let Some(increment) = increment {
+                        self.evaluate(increment)?;
                     }
                 }
 
-                // TODO: Specific error
-                Err(ExprError::ExpectedNumberOperands)
+                Ok(())
             }
 
-            //Handle '<'
-            TokenType::Less => {
-                if let Object::Num(left) = left {
-                    if let Object::Num(right) = right {
-                        return Ok(Object::from(left < right));
-                    }
-                }
+            Stmt::Break { .. } => Err(LoxResult::Break),
+            Stmt::Continue { .. } => Err(LoxResult::Continue),
 
-                // TODO: Specific error
-                Err(ExprError::ExpectedNumberOperands)
-            }
+            Stmt::Function { name, params, body } => {
+                let function = LoxFunction::new(
+                    name.dup(),
+                    params.iter().map(|param| param.dup()).collect(),
+                    body.clone(),
+                    self.environment.clone(),
+                );
 
-            //Handle '<='
-            TokenType::LessEqual => {
-                if let Object::Num(left) = left {
-                    if let Object::Num(right) = right {
-                        return Ok(Object::from(left <= right));
-                    }
-                }
+                self.environment
+                    .borrow_mut()
+                    .define(&name.lexeme, Object::Callable(Callable::Function(Rc::new(function))));
 
-                // TODO: Specific error
-                Err(ExprError::ExpectedNumberOperands)
+                Ok(())
             }
 
-            //Handle '!='
-            TokenType::BangEqual => Ok(Object::from(left != right)),
-
-            //Handle '=='
-            TokenType::EqualEqual => Ok(Object::from(left == right)),
+            Stmt::Return { value, .. } => {
+                let value = match value {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => Object::Nil,
+                };
 
-            _ => Err(ExprError::InvalidExpression),
+                Err(LoxResult::ReturnValue { value })
+            }
         }
     }
 
-    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<Object, ExprError> {
-        match self.environment.get(expr.name.dup()) {
-            Ok(o) => Ok(o),
-            Err(_) => Err(ExprError::InvalidExpression),
-        }
-    }
-}
+    /// Runs `statements` with `environment` swapped in for the duration,
+    /// restoring the caller's environment on the way out (including when a
+    /// statement returns early via `Err`, e.g. `return`/`break`/`continue`).
+    pub fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Cc<RefCell<Environment>>,
+    ) -> Result<(), LoxResult> {
+        let previous = std::mem::replace(&mut self.environment, environment);
 
-impl StmtVisitor<()> for Interpreter {
-    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<(), StmtError> {
-        if let Err(e) = self.evaluate(&stmt.expression) {
-            eprintln!("{}", e);
-        }
+        let result = statements.iter().try_for_each(|statement| self.execute(statement));
 
-        Ok(())
+        self.environment = previous;
+        result
     }
 
-    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<(), StmtError> {
-        if let Ok(value) = self.evaluate(&stmt.expression) {
-            println!("{}", value);
-        }
-
-        Ok(())
-    }
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Object, LoxResult> {
+        match expr {
+            Expr::Literal { value } => Ok(value.clone().unwrap_or(Object::Nil)),
+
+            Expr::Grouping { expression } => self.evaluate(expression),
+
+            Expr::Unary { operator, right } => {
+                let right = self.evaluate(right)?;
+
+                match operator.ttype {
+                    TokenType::Minus => match right {
+                        Object::Num(n) => Ok(Object::Num(-n)),
+                        _ => Err(LoxResult::Runtime {
+                            token: operator.dup(),
+                            error_type: RuntimeErrorType::ExpectedNumberOperand,
+                        }),
+                    },
+                    TokenType::Bang => Ok(Object::from(!self.is_truthy(&right))),
+                    _ => Err(LoxResult::Runtime {
+                        token: operator.dup(),
+                        error_type: RuntimeErrorType::UnreachableCode,
+                    }),
+                }
+            }
 
-    fn visit_var_stmt(&self, stmt: &VarStmt) -> Result<(), StmtError> {
-        let mut value = Object::Nil;
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => self.evaluate_binary(left, operator, right),
+
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.evaluate(left)?;
+
+                match operator.ttype {
+                    TokenType::Or if self.is_truthy(&left) => Ok(left),
+                    TokenType::And if !self.is_truthy(&left) => Ok(left),
+                    _ => self.evaluate(right),
+                }
+            }
 
-        if stmt.initializer.is_some() {
-            value = self.evaluate(stmt.initializer.as_ref().unwrap())?;
-        }
+            // `depth` is `Some(d)` when the Resolver found `name` declared `d`
+            // scopes up from here, and `None` when it's global. Dispatching
+            // on it (rather than always walking the enclosing chain by name)
+            // is what makes a closure keep seeing the variable it captured
+            // even if a same-named one is later declared in between. `slot`
+            // is always `Some` alongside `depth`, letting the lookup index
+            // straight into the target scope's slot vector instead of
+            // hashing `name`.
+            Expr::Variable { name, depth, slot } => match depth {
+                Some(d) => Ok(self
+                    .environment
+                    .borrow()
+                    .get_at(*d, slot.expect("slot resolved alongside depth"))),
+                None => self.globals.borrow_mut().get_binding(name),
+            },
+
+            Expr::Assign {
+                name,
+                value,
+                depth,
+                slot,
+            } => {
+                let value = self.evaluate(value)?;
+
+                match depth {
+                    Some(d) => self
+                        .environment
+                        .borrow_mut()
+                        .assign_at(*d, slot.expect("slot resolved alongside depth"), value.clone()),
+                    None => self.globals.borrow_mut().set_mutable_binding(name, value.clone())?,
+                }
 
-        // TODO
-        // self.environment.define(stmt.name.lexeme.clone(), value);
+                Ok(value)
+            }
 
-        Ok(())
-    }
-}
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => {
+                let callee = self.evaluate(callee)?;
 
-impl Interpreter {
-    pub fn evaluate(&self, expr: &Expr) -> Result<Object, ExprError> {
-        expr.accept(self)
-    }
+                let mut args = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    args.push(self.evaluate(argument)?);
+                }
 
-    pub fn is_truthy(&self, obj: Object) -> bool {
-        !(obj == Object::Nil || obj == Object::False)
-    }
+                let callable = match callee {
+                    Object::Callable(callable) => callable,
+                    _ => {
+                        return Err(LoxResult::Runtime {
+                            token: paren.dup(),
+                            error_type: RuntimeErrorType::InvalidCallObjectType,
+                        })
+                    }
+                };
 
-    pub fn interpret(&self, statements: &[Stmt]) {
-        for statement in statements {
-            self.execute(statement);
-            /*
-            {
-                Ok(obj) => {
-                    println!("Final result: {}", obj);
-                }
-                Err(e) => {
-                    eprintln!("{}", e);
+                if args.len() != callable.arity() {
+                    return Err(LoxResult::Runtime {
+                        token: paren.dup(),
+                        error_type: RuntimeErrorType::InvalidArgsCount,
+                    });
                 }
+
+                callable.call(self, args)
             }
-            */
         }
     }
 
-    pub fn execute(&self, stmt: &Stmt) {
-        match stmt.accept(self) {
-            Ok(_) => (),
-            Err(e) => println!("{:?}", e),
+    fn evaluate_binary(&mut self, left: &Expr, operator: &Token, right: &Expr) -> Result<Object, LoxResult> {
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
+
+        match operator.ttype {
+            TokenType::Minus => match (left, right) {
+                (Object::Num(left), Object::Num(right)) => Ok(Object::Num(left - right)),
+                _ => Err(LoxResult::Runtime {
+                    token: operator.dup(),
+                    error_type: RuntimeErrorType::ExpectedNumberOperands,
+                }),
+            },
+
+            TokenType::Slash => match (left, right) {
+                (Object::Num(left), Object::Num(right)) => Ok(Object::Num(left / right)),
+                _ => Err(LoxResult::Runtime {
+                    token: operator.dup(),
+                    error_type: RuntimeErrorType::ExpectedNumberOperands,
+                }),
+            },
+
+            TokenType::Star => match (left, right) {
+                (Object::Num(left), Object::Num(right)) => Ok(Object::Num(left * right)),
+                _ => Err(LoxResult::Runtime {
+                    token: operator.dup(),
+                    error_type: RuntimeErrorType::ExpectedNumberOperands,
+                }),
+            },
+
+            TokenType::Plus => match (left, right) {
+                (Object::Num(left), Object::Num(right)) => Ok(Object::Num(left + right)),
+                (Object::Str(left), Object::Str(right)) => {
+                    Ok(Object::str(&(left.resolve() + &right.resolve())))
+                }
+                _ => Err(LoxResult::Runtime {
+                    token: operator.dup(),
+                    error_type: RuntimeErrorType::ExpectedAddableOperands,
+                }),
+            },
+
+            TokenType::Greater => match (left, right) {
+                (Object::Num(left), Object::Num(right)) => Ok(Object::from(left > right)),
+                _ => Err(LoxResult::Runtime {
+                    token: operator.dup(),
+                    error_type: RuntimeErrorType::ExpectedNumberOperands,
+                }),
+            },
+
+            TokenType::GreaterEqual => match (left, right) {
+                (Object::Num(left), Object::Num(right)) => Ok(Object::from(left >= right)),
+                _ => Err(LoxResult::Runtime {
+                    token: operator.dup(),
+                    error_type: RuntimeErrorType::ExpectedNumberOperands,
+                }),
+            },
+
+            TokenType::Less => match (left, right) {
+                (Object::Num(left), Object::Num(right)) => Ok(Object::from(left < right)),
+                _ => Err(LoxResult::Runtime {
+                    token: operator.dup(),
+                    error_type: RuntimeErrorType::ExpectedNumberOperands,
+                }),
+            },
+
+            TokenType::LessEqual => match (left, right) {
+                (Object::Num(left), Object::Num(right)) => Ok(Object::from(left <= right)),
+                _ => Err(LoxResult::Runtime {
+                    token: operator.dup(),
+                    error_type: RuntimeErrorType::ExpectedNumberOperands,
+                }),
+            },
+
+            TokenType::BangEqual => Ok(Object::from(left != right)),
+            TokenType::EqualEqual => Ok(Object::from(left == right)),
+
+            _ => Err(LoxResult::Runtime {
+                token: operator.dup(),
+                error_type: RuntimeErrorType::UnreachableCode,
+            }),
         }
     }
+
+    pub fn is_truthy(&self, obj: &Object) -> bool {
+        !matches!(obj, Object::Nil | Object::False)
+    }
 }
@@ -0,0 +1 @@
+pub use crate::token::Object;
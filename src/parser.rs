@@ -1,10 +1,20 @@
-use crate::errors::{LoxResult, ParserErrorType};
+use crate::errors::{LoxResult, ParseError, ParserErrorType};
 use crate::expr::*;
 use crate::object::Object;
 use crate::stmt::*;
 use crate::token::Token;
 use crate::token_type::TokenType;
 
+/// Whether a nesting-aware `synchronize` pass should treat a top-level
+/// semicolon as a recovery stopping point. Parsing a `for (init; cond; incr)`
+/// header wants `Ignore`, since those semicolons are clause separators, not
+/// statement terminators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SemiColonMode {
+    Break,
+    Ignore,
+}
+
 /**
  * Transforms the given array of tokens into an array of statements.
  */
@@ -13,6 +23,20 @@ pub struct Parser<'a> {
     tokens: &'a Vec<Token>,
     /// The current index in the array of tokens.
     current: usize,
+    /// How many loops (`for`/`while`) we are currently nested inside of.
+    /// Used to reject `break`/`continue` parsed outside of any loop.
+    loop_depth: usize,
+    /// When true, a trailing expression statement missing its `;` at the
+    /// end of input is treated as an implicit `print` instead of an error,
+    /// so a REPL can echo the value of whatever was just typed.
+    repl: bool,
+    /// Every parse error recorded so far in the current `parse()` pass.
+    errors: Vec<ParseError>,
+    /// Set by a callee that has already resynchronized the token stream
+    /// itself (e.g. `for_statement()`'s header using `SemiColonMode::Ignore`),
+    /// so `parse()`'s catch-all doesn't resynchronize a second time on top
+    /// of it.
+    recovered: bool,
 }
 
 impl<'a> Parser<'a> {
@@ -20,14 +44,41 @@ impl<'a> Parser<'a> {
      * Instanciates a parser from an array of tokens.
      */
     pub fn new(tokens: &Vec<Token>) -> Parser {
-        Parser { tokens, current: 0 }
+        Parser {
+            tokens,
+            current: 0,
+            loop_depth: 0,
+            repl: false,
+            errors: Vec::new(),
+            recovered: false,
+        }
+    }
+
+    /**
+     * Instanciates a parser in REPL mode: a trailing expression statement
+     * with no `;` at the end of input is parsed as an implicit `print`.
+     */
+    pub fn new_repl(tokens: &Vec<Token>) -> Parser {
+        Parser {
+            tokens,
+            current: 0,
+            loop_depth: 0,
+            repl: true,
+            errors: Vec::new(),
+            recovered: false,
+        }
     }
 
     /**
      * Main parsing function that transforms the array of tokens into an array of statements
      * if they are parsable.
+     *
+     * Unlike a fail-fast parser, this collects every syntax error found across the whole
+     * token stream instead of stopping at the first one: each failed declaration is
+     * recorded and the parser resynchronizes at the next statement boundary so later,
+     * unrelated errors are still reported in the same pass.
      */
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, LoxResult> {
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
         // Output array of parsed statements
         let mut statements = Vec::new();
 
@@ -35,66 +86,69 @@ impl<'a> Parser<'a> {
         while !self.is_at_end() {
             // Parse the next tokens into a declaration.
             match self.declaration() {
-                Ok(s) => match s {
-                    // If the parsed declaration is a statement, save it
-                    Some(s) => statements.push(s),
-                    None => {}
-                },
-                // If it is an error, return it
+                Ok(s) => statements.push(s),
+                // If it is an error, record it and recover at the next statement boundary
                 Err(e) => {
-                    return Err(e);
+                    // A missing-semicolon error already leaves the cursor sitting right at
+                    // the start of the next statement, so there is nothing to synchronize
+                    // past -- doing so anyway would needlessly discard that statement.
+                    let already_at_boundary = matches!(
+                        e,
+                        LoxResult::Parser {
+                            error_type: ParserErrorType::MissingSemicolon,
+                            ..
+                        }
+                    );
+
+                    self.errors.push(Self::to_parse_error(e));
+
+                    // A callee may already have resynchronized itself (e.g. a `for`
+                    // header, which needs `SemiColonMode::Ignore` instead of this
+                    // catch-all's `Break`); don't do it again on top of that.
+                    let already_recovered = std::mem::take(&mut self.recovered);
+
+                    if !already_at_boundary && !already_recovered {
+                        self.synchronize_nested(SemiColonMode::Break);
+                    }
                 }
             }
         }
 
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
+        }
+
         // Return the parsed statements
         Ok(statements)
     }
 
+    /// Converts a parser's `LoxResult` into the lighter `ParseError` that
+    /// `parse()` accumulates, keeping the offending token and a rendered message.
+    fn to_parse_error(error: LoxResult) -> ParseError {
+        let message = error.to_string();
+
+        match error {
+            LoxResult::Parser { token, .. } => ParseError { token, message },
+            _ => unreachable!("the parser only ever produces LoxResult::Parser errors"),
+        }
+    }
+
     /**
      * Parses the next tokens into a declaration statement.
      */
-    fn declaration(&mut self) -> Result<Option<Stmt>, LoxResult> {
+    fn declaration(&mut self) -> Result<Stmt, LoxResult> {
         // If the next token is 'fun', parse the function definition
         if self.matchs_next(&[TokenType::Fun]) {
-            match self.function("function") {
-                Ok(s) => return Ok(Some(s)),
-                Err(e) => {
-                    eprintln!("{}", e);
-                    self.synchronize();
-                }
-            }
+            return self.function("function");
         }
 
         // If the next token is 'var', parse the variable declaration
         if self.matchs_next(&[TokenType::Var]) {
-            match self.var_declaration() {
-                // Return the parsed variable declaration statement
-                Ok(s) => {
-                    return Ok(Some(s));
-                }
-                // If it was an error, print it and synchronize
-                Err(e) => {
-                    eprintln!("{e}");
-                    self.synchronize();
-                }
-            }
-        }
-
-        // Otherwise, parse it asa statement
-        match self.statement() {
-            // Return the parsed statement
-            Ok(s) => {
-                return Ok(Some(s));
-            }
-            // If it errored, print it and synchronize
-            Err(e) => {
-                eprintln!("{e}");
-                self.synchronize();
-            }
+            return self.var_declaration();
         }
 
-        Ok(None)
+        // Otherwise, parse it as a statement
+        self.statement()
     }
 
     fn function(&mut self, kind: &str) -> Result<Stmt, LoxResult> {
@@ -161,10 +215,7 @@ impl<'a> Parser<'a> {
         };
 
         // Check if we got an ending ';' after the variable declaration
-        self.consume(
-            TokenType::Semicolon,
-            "Expected ';' after variable declaration.",
-        )?;
+        self.consume_semicolon("Expected ';' after variable declaration.")?;
 
         // Return a non-initialized VarStmt
         Ok(Stmt::Var { name, initializer })
@@ -199,6 +250,16 @@ impl<'a> Parser<'a> {
             return self.while_statement();
         }
 
+        // Check if the next token is a 'break' statement
+        if self.matchs_next(&[TokenType::Break]) {
+            return self.break_statement();
+        }
+
+        // Check if the next token is a 'continue' statement
+        if self.matchs_next(&[TokenType::Continue]) {
+            return self.continue_statement();
+        }
+
         // Check if the next token is a scope opening left brace '{'
         if self.matchs_next(&[TokenType::LeftBrace]) {
             let stmts = self.block_statement()?;
@@ -210,6 +271,81 @@ impl<'a> Parser<'a> {
     }
 
     fn for_statement(&mut self) -> Result<Stmt, LoxResult> {
+        // Parse the `(init; cond; incr)` header on its own. On error,
+        // recover right here with `SemiColonMode::Ignore` -- the header's own
+        // semicolons separate clauses rather than terminate a statement, so
+        // the catch-all `synchronize_nested(Break)` in `parse()` would stop
+        // at the first of them instead of skipping the whole broken header.
+        let (initializer, mut condition, increment) = match self.for_header() {
+            Ok(header) => header,
+            Err(e) => {
+                self.synchronize_nested(SemiColonMode::Ignore);
+                self.recovered = true;
+                return Err(e);
+            }
+        };
+
+        /*
+         * We will now basically transform the for loop into a while loop here.
+         *
+         * Written for loop:
+         * for (var i = 0; i < 10; i = i + 1) print i;
+         *
+         * Executed while loop:
+         * {
+         * var i = 0;
+         * while (i < 10) {
+         *  print i;
+         *  i = i + 1;
+         * }
+        }
+        *
+        * Note: the increment is kept on the `Stmt::While` node itself rather
+        * than appended to the body, so that a `continue` inside the body
+        * still runs it on every iteration instead of skipping it.
+        */
+
+        self.loop_depth += 1;
+
+        // Parse the body statements of the for loop
+        // e.g in the example above: "print i;"
+        let body = self.statement();
+
+        self.loop_depth -= 1;
+
+        let body = body?;
+
+        // If there weren't any condition, write a true literal expression instead to a perform a
+        // while (true) infinite loop.
+        if condition.is_none() {
+            condition = Some(Expr::Literal {
+                value: Some(Object::True),
+            });
+        }
+
+        // Put the current body into a while expression with its condition and increment
+        let mut body = Stmt::While {
+            condition: condition.unwrap(),
+            body: Box::new(body),
+            increment,
+        };
+
+        // If there were any initializer, put it at the beggining of the new tranformed code
+        // e.g in the example above: "var i = 0;"
+        if initializer.is_some() {
+            body = Stmt::Block {
+                statements: vec![initializer.unwrap(), body],
+            };
+        }
+
+        Ok(body)
+    }
+
+    /// Parses the `(init; cond; incr)` clause of a `for` statement, split
+    /// out of `for_statement` so its error path can resynchronize with
+    /// `SemiColonMode::Ignore` instead of the default `Break` behavior.
+    #[allow(clippy::type_complexity)]
+    fn for_header(&mut self) -> Result<(Option<Stmt>, Option<Expr>, Option<Expr>), LoxResult> {
         // The next token to come after 'for' must be an opening '('
         self.consume(
             TokenType::LeftParen,
@@ -251,57 +387,45 @@ impl<'a> Parser<'a> {
             "Expected closing ')' after for statement.",
         )?;
 
-        /*
-         * We will now basically transform the for loop into a while loop here.
-         *
-         * Written for loop:
-         * for (var i = 0; i < 10; i = i + 1) print i;
-         *
-         * Executed while loop:
-         * {
-         * var i = 0;
-         * while (i < 10) {
-         *  print i;
-         *  i = i + 1;
-         * }
-        }
-        */
-
-        // Parse the body statements of the for loop
-        // e.g in the example above: "print i;"
-        let mut body = self.statement()?;
+        Ok((initializer, condition, increment))
+    }
 
-        // If there were an increment, write an iteration of it at the end of the body.
-        // e.g in the example above: "i = i + 1"
-        if let Some(i) = increment {
-            body = Stmt::Block {
-                statements: vec![body, Stmt::Expression { expression: i }],
-            }
-        }
+    /**
+     * Parses a 'break' statement, erroring if it is not nested in a loop.
+     */
+    fn break_statement(&mut self) -> Result<Stmt, LoxResult> {
+        let keyword = self.previous();
 
-        // If there weren't any condition, write a true literal expression instead to a perform a
-        // while (true) infinite loop.
-        if condition.is_none() {
-            condition = Some(Expr::Literal {
-                value: Some(Object::True),
+        if self.loop_depth == 0 {
+            return Err(LoxResult::Parser {
+                token: keyword,
+                error_type: ParserErrorType::LoopControlOutsideLoop,
+                msg: "".to_string(),
             });
         }
 
-        // Put the current body into a while expression with its condition
-        body = Stmt::While {
-            condition: condition.unwrap(),
-            body: Box::new(body),
-        };
+        self.consume_semicolon("Expected ';' after 'break'.")?;
 
-        // If there were any initializer, put it at the beggining of the new tranformed code
-        // e.g in the example above: "var i = 0;"
-        if initializer.is_some() {
-            body = Stmt::Block {
-                statements: vec![initializer.unwrap(), body],
-            };
+        Ok(Stmt::Break { keyword })
+    }
+
+    /**
+     * Parses a 'continue' statement, erroring if it is not nested in a loop.
+     */
+    fn continue_statement(&mut self) -> Result<Stmt, LoxResult> {
+        let keyword = self.previous();
+
+        if self.loop_depth == 0 {
+            return Err(LoxResult::Parser {
+                token: keyword,
+                error_type: ParserErrorType::LoopControlOutsideLoop,
+                msg: "".to_string(),
+            });
         }
 
-        Ok(body)
+        self.consume_semicolon("Expected ';' after 'continue'.")?;
+
+        Ok(Stmt::Continue { keyword })
     }
 
     fn if_statement(&mut self) -> Result<Stmt, LoxResult> {
@@ -333,7 +457,7 @@ impl<'a> Parser<'a> {
         // Parse the value to print as an expression
         let value = self.expression()?;
         // Check the statement ends with a semicolon.
-        self.consume(TokenType::Semicolon, "Expected ';' after value.")?;
+        self.consume_semicolon("Expected ';' after value.")?;
         // Return the parsed print statement
         Ok(Stmt::Print { expression: value })
     }
@@ -349,7 +473,7 @@ impl<'a> Parser<'a> {
             value = Some(self.expression()?);
         }
 
-        self.consume(TokenType::Semicolon, "Expected ';' after return statement.")?;
+        self.consume_semicolon("Expected ';' after return statement.")?;
 
         Ok(Stmt::Return { keyword, value })
     }
@@ -361,25 +485,24 @@ impl<'a> Parser<'a> {
         self.consume(TokenType::LeftParen, "Expected '(' after while statement.")?;
         let condition = self.expression()?;
         self.consume(
-            TokenType::LeftParen,
+            TokenType::RightParen,
             "Expected closing ')' after while statement.",
         )?;
-        let body = self.statement()?;
+
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        let body = body?;
 
         Ok(Stmt::While {
             condition,
             body: Box::new(body),
+            increment: None,
         })
     }
 
     fn block_statement(&mut self) -> Result<Vec<Stmt>, LoxResult> {
-        let mut stmts = Vec::new();
-
-        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
-            if let Some(s) = self.declaration()? {
-                stmts.push(s);
-            }
-        }
+        let stmts = self.statement_list(&[TokenType::RightBrace])?;
 
         self.consume(
             TokenType::RightBrace,
@@ -389,14 +512,39 @@ impl<'a> Parser<'a> {
         Ok(stmts)
     }
 
+    /**
+     * Parses declarations until the next token is one of `terminators` (left unconsumed
+     * for the caller to check/consume itself) or the end of input is reached. This is the
+     * shared routine behind any construct that parses a list of statements up to some
+     * closing token -- blocks (and so function bodies) stop at `RightBrace`. The program
+     * root does not go through it: `parse()` runs its own loop so it can record each failed
+     * declaration and resynchronize, whereas this routine returns on the first error.
+     */
+    fn statement_list(&mut self, terminators: &[TokenType]) -> Result<Vec<Stmt>, LoxResult> {
+        let mut stmts = Vec::new();
+
+        while !self.check_any(terminators) && !self.is_at_end() {
+            stmts.push(self.declaration()?);
+        }
+
+        Ok(stmts)
+    }
+
     /**
      * Parses the next tokens in an expression statement.
      */
     fn expression_statement(&mut self) -> Result<Stmt, LoxResult> {
         // Parse the expression
         let expr = self.expression()?;
+
+        // In REPL mode, a trailing expression with no ';' at the end of input is
+        // echoed automatically instead of erroring, so `> 1 + 1` just works.
+        if self.repl && !self.check(TokenType::Semicolon) && self.is_at_end() {
+            return Ok(Stmt::Print { expression: expr });
+        }
+
         // Check the expression ends with a semicolon.
-        self.consume(TokenType::Semicolon, "Expected ';' after expression.")?;
+        self.consume_semicolon("Expected ';' after expression.")?;
         // Return the parsed expression
         Ok(Stmt::Expression { expression: expr })
     }
@@ -416,10 +564,12 @@ impl<'a> Parser<'a> {
             let equals = self.previous();
             let value = self.assignment()?;
 
-            if let Expr::Variable { name } = expr {
+            if let Expr::Variable { name, .. } = expr {
                 return Ok(Expr::Assign {
                     name,
                     value: Box::new(value),
+                    depth: None,
+                    slot: None,
                 });
             }
 
@@ -687,6 +837,8 @@ impl<'a> Parser<'a> {
         if self.matchs_next(&[TokenType::Identifier]) {
             return Ok(Expr::Variable {
                 name: self.previous(),
+                depth: None,
+                slot: None,
             });
         }
 
@@ -730,6 +882,45 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /**
+     * Like `consume(TokenType::Semicolon, msg)`, but recognizes the common case where the
+     * user simply forgot the `;` and the next token already starts a new statement (a
+     * statement keyword, or an identifier). In that case it reports a targeted
+     * "missing semicolon" diagnostic pointing at the end of the *previous* token instead
+     * of at the next statement, and leaves that next statement untouched so it still
+     * parses normally afterwards.
+     */
+    fn consume_semicolon(&mut self, msg: &str) -> Result<Token, LoxResult> {
+        if self.check(TokenType::Semicolon) {
+            return Ok(self.advance());
+        }
+
+        if matches!(
+            self.peek().ttype,
+            TokenType::Var
+                | TokenType::If
+                | TokenType::While
+                | TokenType::For
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Fun
+                | TokenType::Class
+                | TokenType::Identifier
+        ) {
+            return Err(LoxResult::Parser {
+                token: self.previous(),
+                error_type: ParserErrorType::MissingSemicolon,
+                msg: msg.to_string(),
+            });
+        }
+
+        Err(LoxResult::Parser {
+            token: self.tokens[self.current].dup(),
+            error_type: ParserErrorType::InvalidConsumeType,
+            msg: msg.to_string(),
+        })
+    }
+
     /**
      * Checks tha the next token's type is one of the wanted one.
      * Returns true if it is, false otherwise.
@@ -763,6 +954,13 @@ impl<'a> Parser<'a> {
         self.peek().ttype == ttype
     }
 
+    /**
+     * Checks if the next token's type is any of the given `types`.
+     */
+    fn check_any(&self, types: &[TokenType]) -> bool {
+        types.iter().any(|&ttype| self.check(ttype))
+    }
+
     /**
      * Returns the next token in the array and increment the current index by one.
      */
@@ -802,35 +1000,60 @@ impl<'a> Parser<'a> {
     }
 
     /**
-     * Advances if the tokens until reaching a ';' that would mark the end of the bad code.
-     * This function allows for the parser to continue process code even after encountering an
-     * error it in.
+     * Advances the tokens until reaching a statement boundary, so the parser can
+     * continue processing code after encountering an error in it. A `Semicolon` only
+     * counts as a recovery stopping point once `brace_depth` and `paren_depth` are
+     * both back to zero, so an error inside a nested block or a `for (init; cond;
+     * incr)` header doesn't stop at one of the inner semicolons/braces. `mode` lets
+     * callers parsing a `for` header ask to ignore top-level semicolons entirely,
+     * since those separate clauses rather than terminate a statement.
      */
-    fn synchronize(&mut self) {
+    fn synchronize_nested(&mut self, mode: SemiColonMode) {
+        let mut brace_depth = 0usize;
+        let mut paren_depth = 0usize;
+
         // Parse at least one token
         self.advance();
 
-        // We can go up to the end of the whole code if there aren't any way to recover before
         while !self.is_at_end() {
-            // If we find a semicolon, we can return
-            if self.previous().ttype == TokenType::Semicolon {
-                return;
+            match self.previous().ttype {
+                TokenType::LeftBrace => brace_depth += 1,
+                TokenType::LeftParen => paren_depth += 1,
+                TokenType::RightParen => paren_depth = paren_depth.saturating_sub(1),
+                TokenType::Semicolon => {
+                    if mode == SemiColonMode::Break && brace_depth == 0 && paren_depth == 0 {
+                        return;
+                    }
+                }
+                _ => {}
             }
 
-            // Why is that here ?
-            match self.peek().ttype {
-                TokenType::Class => {}
-                TokenType::Fun => {}
-                TokenType::Var => {}
-                TokenType::For => {}
-                TokenType::If => {}
-                TokenType::While => {}
-                TokenType::Print => {}
-                TokenType::Return => {} // TokenType::Class => {}
-                _ => {}
+            // A closing brace at depth zero means we've popped back out of the enclosing
+            // block: stop here without consuming it.
+            if self.peek().ttype == TokenType::RightBrace {
+                if brace_depth == 0 {
+                    return;
+                }
+                brace_depth -= 1;
+            }
+
+            // If the next token starts a new statement at depth zero, stop here too.
+            if brace_depth == 0 && paren_depth == 0 {
+                match self.peek().ttype {
+                    TokenType::Class
+                    | TokenType::Fun
+                    | TokenType::Var
+                    | TokenType::For
+                    | TokenType::If
+                    | TokenType::While
+                    | TokenType::Print
+                    | TokenType::Return
+                    | TokenType::Break
+                    | TokenType::Continue => return,
+                    _ => {}
+                }
             }
 
-            // Advance by one token
             self.advance();
         }
     }
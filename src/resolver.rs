@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use crate::errors::{LoxResult, ResolverErrorType};
+use crate::expr::Expr;
+use crate::stmt::Stmt;
+use crate::token::Token;
+
+/// A variable's state within a single `Resolver` scope: whether it has
+/// finished being defined (`false` while its own initializer is still being
+/// resolved), and the slot it occupies in that scope's `Environment::slots`,
+/// assigned in declaration order.
+struct Binding {
+    defined: bool,
+    slot: usize,
+}
+
+/// A single lexical scope: the bindings declared in it so far, plus a
+/// counter tracking how many `define` calls this scope will see at runtime
+/// (which, unlike `bindings.len()`, keeps counting up when a name is
+/// redeclared, matching `Environment::define` always appending a new slot).
+#[derive(Default)]
+struct Scope {
+    bindings: HashMap<String, Binding>,
+    next_slot: usize,
+}
+
+/**
+ * Walks the statements produced by the parser once before interpretation,
+ * resolving each variable access/assignment to the number of scopes between
+ * it and the scope that declares it, plus the slot it occupies within that
+ * scope. This fixes closure-capture/shadowing bugs and lets the interpreter
+ * jump straight to `ancestor(depth).slots[slot]` instead of walking the
+ * chain and hashing names every time.
+ */
+pub struct Resolver {
+    /// Stack of scopes, innermost last.
+    scopes: Vec<Scope>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Resolver { scopes: Vec::new() }
+    }
+
+    /**
+     * Resolves every statement in order, in the current scope.
+     */
+    pub fn resolve_stmts(&mut self, stmts: &mut Vec<Stmt>) -> Result<(), LoxResult> {
+        for stmt in stmts {
+            self.resolve_stmt(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    fn resolve_stmt(&mut self, stmt: &mut Stmt) -> Result<(), LoxResult> {
+        match stmt {
+            Stmt::Block { statements } => {
+                self.begin_scope();
+                self.resolve_stmts(statements)?;
+                self.end_scope();
+            }
+
+            Stmt::Var { name, initializer } => {
+                self.declare(name);
+                if let Some(init) = initializer {
+                    self.resolve_expr(init)?;
+                }
+                self.define(name);
+            }
+
+            Stmt::Function { name, params, body } => {
+                self.declare(name);
+                self.define(name);
+
+                self.begin_scope();
+                for param in params.iter() {
+                    self.declare(param);
+                    self.define(param);
+                }
+                self.resolve_stmts(body)?;
+                self.end_scope();
+            }
+
+            Stmt::Expression { expression } => self.resolve_expr(expression)?,
+
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.resolve_expr(condition)?;
+                self.resolve_stmt(then_branch)?;
+                if let Some(else_branch) = else_branch.as_mut() {
+                    self.resolve_stmt(else_branch)?;
+                }
+            }
+
+            Stmt::Print { expression } => self.resolve_expr(expression)?,
+
+            Stmt::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.resolve_expr(value)?;
+                }
+            }
+
+            Stmt::While {
+                condition,
+                body,
+                increment,
+            } => {
+                self.resolve_expr(condition)?;
+                self.resolve_stmt(body)?;
+                if let Some(increment) = increment {
+                    self.resolve_expr(increment)?;
+                }
+            }
+
+            Stmt::Break { .. } | Stmt::Continue { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    fn resolve_expr(&mut self, expr: &mut Expr) -> Result<(), LoxResult> {
+        match expr {
+            Expr::Variable { name, depth, slot } => {
+                if let Some(scope) = self.scopes.last() {
+                    if matches!(scope.bindings.get(&name.lexeme), Some(b) if !b.defined) {
+                        return Err(LoxResult::Resolver {
+                            token: name.dup(),
+                            error_type: ResolverErrorType::VariableNotInitialized,
+                        });
+                    }
+                }
+
+                (*depth, *slot) = self.resolve_local(name);
+            }
+
+            Expr::Assign {
+                name,
+                value,
+                depth,
+                slot,
+            } => {
+                self.resolve_expr(value)?;
+                (*depth, *slot) = self.resolve_local(name);
+            }
+
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.resolve_expr(left)?;
+                self.resolve_expr(right)?;
+            }
+
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.resolve_expr(callee)?;
+                for argument in arguments {
+                    self.resolve_expr(argument)?;
+                }
+            }
+
+            Expr::Grouping { expression } => self.resolve_expr(expression)?,
+
+            Expr::Unary { right, .. } => self.resolve_expr(right)?,
+
+            Expr::Literal { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Marks `name` as declared but not yet defined in the innermost scope,
+    /// assigning it the next free slot in that scope.
+    fn declare(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            let slot = scope.next_slot;
+            scope.next_slot += 1;
+            scope.bindings.insert(name.lexeme.clone(), Binding { defined: false, slot });
+        }
+    }
+
+    /// Marks `name` as fully defined in the innermost scope.
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if let Some(binding) = scope.bindings.get_mut(&name.lexeme) {
+                binding.defined = true;
+            }
+        }
+    }
+
+    /// Searches the scope stack from innermost outward for `name`, returning
+    /// `(depth, slot)` -- the number of hops to the scope that declares it
+    /// and the slot it occupies there -- or `None` if it is not found in any
+    /// scope (i.e. it is global).
+    fn resolve_local(&self, name: &Token) -> (Option<usize>, Option<usize>) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(binding) = scope.bindings.get(&name.lexeme) {
+                return (Some(depth), Some(binding.slot));
+            }
+        }
+
+        (None, None)
+    }
+}
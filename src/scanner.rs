@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::errors::{LoxError, ScannerErrorType};
+use crate::errors::{LoxResult, ScannerErrorType};
 use crate::token::*;
 use crate::token_type::*;
 
@@ -8,7 +8,9 @@ use lazy_static::lazy_static;
 lazy_static! {
     static ref RESERVED_IDENTIFIERS: HashMap<String, TokenType> = HashMap::from([
         ("and".to_string(), TokenType::And),
+        ("break".to_string(), TokenType::Break),
         ("class".to_string(), TokenType::Class),
+        ("continue".to_string(), TokenType::Continue),
         ("else".to_string(), TokenType::Else),
         ("false".to_string(), TokenType::False),
         ("for".to_string(), TokenType::For),
@@ -29,134 +31,214 @@ lazy_static! {
 pub struct Scanner {
     pub source: String,
     pub tokens: Vec<Token>,
+    /// The source, decoded once into chars so that cursor operations below
+    /// are O(1) instead of re-walking the string from the start every time.
+    chars: Vec<char>,
     start: usize,
     current: usize,
     line: usize,
+    /// Index (in `chars`) of the first character of the current line, used
+    /// to compute a column for diagnostics as `current - line_start`.
+    line_start: usize,
 }
 
 impl Scanner {
     pub fn new(source: String) -> Scanner {
+        let chars = source.chars().collect();
+
         Scanner {
             source,
             tokens: Vec::new(),
+            chars,
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+        }
+    }
+
+    /// Scans the whole source, collecting every scanner error instead of
+    /// bailing on the first one, and returning them all together if any
+    /// were found.
+    pub fn scan_tokens(&mut self) -> Result<&Vec<Token>, Vec<LoxResult>> {
+        let mut errors = Vec::new();
+
+        loop {
+            match self.next_token() {
+                Ok(token) => {
+                    let at_eof = token.ttype == TokenType::Eof;
+                    self.tokens.push(token);
+                    if at_eof {
+                        break;
+                    }
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
         }
+
+        Ok(&self.tokens)
     }
 
-    pub fn scan_tokens(&mut self) -> Result<&Vec<Token>, LoxError> {
-        while !self.is_at_end() {
+    /// Pulls and returns the next single token, advancing the cursor past
+    /// it. Returns the `Eof` token once the source is exhausted, and keeps
+    /// returning it on every subsequent call.
+    ///
+    /// This is the streaming counterpart to `scan_tokens`: useful for a
+    /// single-pass compiler, or a REPL that wants to stop scanning early.
+    pub fn next_token(&mut self) -> Result<Token, LoxResult> {
+        loop {
+            if self.is_at_end() {
+                return Ok(Token::eof(self.line, self.current));
+            }
+
             self.start = self.current;
 
-            self.scan_token()?;
+            if let Some(token) = self.scan_token()? {
+                return Ok(token);
+            }
+
+            // Otherwise the last scanned character was whitespace, a
+            // newline, or a comment, none of which produce a token -- loop
+            // around and scan the next one.
         }
+    }
 
-        self.tokens.push(Token::eof(self.line, self.current));
+    /// Builds a scanner diagnostic pointing at `line`/`column`, rendering
+    /// the source line starting at `line_start` alongside a caret.
+    fn error_at(
+        &self,
+        c: char,
+        error_type: ScannerErrorType,
+        line: usize,
+        line_start: usize,
+        column: usize,
+    ) -> LoxResult {
+        let source_line = self
+            .chars
+            .iter()
+            .skip(line_start)
+            .take_while(|&&ch| ch != '\n')
+            .collect();
+
+        LoxResult::Scanner {
+            c,
+            error_type,
+            line,
+            column,
+            source_line,
+        }
+    }
 
-        Ok(&self.tokens)
+    /// Builds a diagnostic for the current cursor position.
+    fn error(&self, c: char, error_type: ScannerErrorType) -> LoxResult {
+        self.error_at(
+            c,
+            error_type,
+            self.line,
+            self.line_start,
+            self.current - self.line_start,
+        )
     }
 
     fn is_at_end(&self) -> bool {
-        self.current == self.source.len()
+        self.current == self.chars.len()
     }
 
-    fn scan_token(&mut self) -> Result<(), LoxError> {
+    /// Scans a single token starting at `self.start`. Returns `None` for
+    /// characters that don't produce a token themselves (whitespace,
+    /// newlines, comments) so callers just loop around and try again.
+    ///
+    /// Shared by both the batch (`scan_tokens`) and streaming (`next_token`)
+    /// entry points so the single-token logic only lives in one place.
+    fn scan_token(&mut self) -> Result<Option<Token>, LoxResult> {
         let c = self.advance();
-        match c {
+        let token = match c {
             // Single character lexemes
-            '(' => self.tokens.push(Token::left_paren(self.line, self.current)),
-            ')' => self
-                .tokens
-                .push(Token::right_paren(self.line, self.current)),
-            '{' => self.tokens.push(Token::left_brace(self.line, self.current)),
-            '}' => self
-                .tokens
-                .push(Token::right_brace(self.line, self.current)),
-            ',' => self.tokens.push(Token::comma(self.line, self.current)),
-            '.' => self.tokens.push(Token::dot(self.line, self.current)),
-            '-' => self.tokens.push(Token::minus(self.line, self.current)),
-            '+' => self.tokens.push(Token::plus(self.line, self.current)),
-            ';' => self.tokens.push(Token::semicolon(self.line, self.current)),
-            '*' => self.tokens.push(Token::star(self.line, self.current)),
+            '(' => Some(Token::left_paren(self.line, self.current)),
+            ')' => Some(Token::right_paren(self.line, self.current)),
+            '{' => Some(Token::left_brace(self.line, self.current)),
+            '}' => Some(Token::right_brace(self.line, self.current)),
+            ',' => Some(Token::comma(self.line, self.current)),
+            '.' => Some(Token::dot(self.line, self.current)),
+            '-' => Some(Token::minus(self.line, self.current)),
+            '+' => Some(Token::plus(self.line, self.current)),
+            ';' => Some(Token::semicolon(self.line, self.current)),
+            '*' => Some(Token::star(self.line, self.current)),
 
             // Two character lexemes
-            '!' => {
-                if self.match_next('=') {
-                    self.tokens.push(Token::bang_equal(self.line, self.current));
-                } else {
-                    self.tokens.push(Token::bang(self.line, self.current));
-                }
-            }
-            '=' => {
-                if self.match_next('=') {
-                    self.tokens
-                        .push(Token::equal_equal(self.line, self.current));
-                } else {
-                    self.tokens.push(Token::equal(self.line, self.current));
-                }
-            }
-            '<' => {
-                if self.match_next('=') {
-                    self.tokens.push(Token::less_equal(self.line, self.current));
-                } else {
-                    self.tokens.push(Token::less(self.line, self.current));
-                }
-            }
-            '>' => {
-                if self.match_next('=') {
-                    self.tokens
-                        .push(Token::greater_equal(self.line, self.current));
-                } else {
-                    self.tokens.push(Token::greater(self.line, self.current));
-                }
-            }
-
-            // Special handling of '/' because it can be a comment.
+            '!' => Some(if self.match_next('=') {
+                Token::bang_equal(self.line, self.current)
+            } else {
+                Token::bang(self.line, self.current)
+            }),
+            '=' => Some(if self.match_next('=') {
+                Token::equal_equal(self.line, self.current)
+            } else {
+                Token::equal(self.line, self.current)
+            }),
+            '<' => Some(if self.match_next('=') {
+                Token::less_equal(self.line, self.current)
+            } else {
+                Token::less(self.line, self.current)
+            }),
+            '>' => Some(if self.match_next('=') {
+                Token::greater_equal(self.line, self.current)
+            } else {
+                Token::greater(self.line, self.current)
+            }),
+
+            // Special handling of '/' because it can be a line or block comment.
             '/' => {
                 if self.match_next('/') {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    None
+                } else if self.match_next('*') {
+                    self.scan_block_comment()?;
+                    None
                 } else {
-                    self.tokens.push(Token::slash(self.line, self.current));
+                    Some(Token::slash(self.line, self.current))
                 }
             }
 
             // Meaningless characters
-            ' ' => {}
-            '\r' => {}
-            '\t' => {}
+            ' ' => None,
+            '\r' => None,
+            '\t' => None,
 
             // Newline
-            '\n' => self.line += 1,
+            '\n' => {
+                self.line += 1;
+                self.line_start = self.current;
+                None
+            }
 
             // String literals
-            '"' => {
-                self.scan_string()?;
-            }
+            '"' => Some(self.scan_string()?),
 
             // Unexpected character, throw an error
             _ => {
                 // Number literals
                 if c.is_digit(10) {
-                    self.scan_number()?;
+                    Some(self.scan_number())
                 } else if c.is_alphabetic() || c == '_' {
-                    self.scan_identifier();
+                    Some(self.scan_identifier())
                 } else {
-                    return Err(LoxError::ScannerError {
-                        c,
-                        error_type: ScannerErrorType::InvalidCharacter,
-                    });
+                    return Err(self.error(c, ScannerErrorType::InvalidCharacter));
                 }
             }
-        }
+        };
 
-        Ok(())
+        Ok(token)
     }
 
     fn advance(&mut self) -> char {
-        let c = self.source.chars().nth(self.current).unwrap();
+        let c = self.chars[self.current];
         self.current += 1;
         c
     }
@@ -166,7 +248,7 @@ impl Scanner {
             return false;
         }
 
-        if self.source.chars().nth(self.current).unwrap() != expected {
+        if self.chars[self.current] != expected {
             return false;
         }
 
@@ -179,46 +261,136 @@ impl Scanner {
             return '\0';
         }
 
-        return self.source.chars().nth(self.current).unwrap();
+        self.chars[self.current]
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
+        if self.current + 1 >= self.chars.len() {
             return '\0';
         }
 
-        return self.source.chars().nth(self.current + 1).unwrap();
+        self.chars[self.current + 1]
     }
 
-    fn scan_string(&mut self) -> Result<(), LoxError> {
-        // Keep scanning until we find the closing " or we get to the end of the source code
-        while self.peek() != '"' && !self.is_at_end() {
+    /// Builds an owned string from the `[start, end)` char range, mirroring
+    /// what `self.source.get(start..end)` used to do when indices were byte
+    /// offsets rather than char offsets.
+    fn slice(&self, start: usize, end: usize) -> String {
+        self.chars[start..end].iter().collect()
+    }
+
+    /// Consumes a `/* ... */` block comment, allowing it to nest so that
+    /// `/* outer /* inner */ still comment */` is consumed as a single
+    /// comment. `self.current` is already past the opening `/*`.
+    fn scan_block_comment(&mut self) -> Result<(), LoxResult> {
+        let open_line = self.line;
+        let open_line_start = self.line_start;
+        let open_start = self.start;
+
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                return Err(self.error_at(
+                    '*',
+                    ScannerErrorType::UnterminatedComment,
+                    open_line,
+                    open_line_start,
+                    open_start - open_line_start,
+                ));
+            }
+
             if self.peek() == '\n' {
                 self.line += 1;
+                self.line_start = self.current + 1;
+                self.advance();
+                continue;
+            }
+
+            if self.peek() == '/' && self.peek_next() == '*' {
+                self.advance();
+                self.advance();
+                depth += 1;
+                continue;
+            }
+
+            if self.peek() == '*' && self.peek_next() == '/' {
+                self.advance();
+                self.advance();
+                depth -= 1;
+                continue;
             }
+
             self.advance();
         }
 
+        Ok(())
+    }
+
+    fn scan_string(&mut self) -> Result<Token, LoxResult> {
+        // Remember where the string opened so an unterminated string is
+        // reported there rather than at EOF.
+        let open_line = self.line;
+        let open_line_start = self.line_start;
+
+        // Decode the string into an owned buffer instead of slicing the raw
+        // source, so that escape sequences can be unescaped as we go.
+        let mut decoded = String::new();
+
+        while self.peek() != '"' && !self.is_at_end() {
+            if self.peek() == '\n' {
+                self.line += 1;
+                self.line_start = self.current + 1;
+                decoded.push(self.advance());
+                continue;
+            }
+
+            if self.peek() == '\\' {
+                self.advance();
+                let escaped = self.peek();
+
+                let unescaped = match escaped {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    '"' => '"',
+                    '\\' => '\\',
+                    _ => {
+                        let err = self.error(escaped, ScannerErrorType::InvalidEscape);
+                        // Skip past the bad escape character so the next
+                        // scan iteration resumes right after it, rather
+                        // than seeing it again and reporting it forever.
+                        self.advance();
+                        return Err(err);
+                    }
+                };
+
+                decoded.push(unescaped);
+                self.advance();
+                continue;
+            }
+
+            decoded.push(self.advance());
+        }
+
         // If we did not find the end of the string, error out
         if self.is_at_end() {
-            return Err(LoxError::ScannerError {
-                c: '"',
-                error_type: ScannerErrorType::UnterminatedString,
-            });
+            return Err(self.error_at(
+                '"',
+                ScannerErrorType::UnterminatedString,
+                open_line,
+                open_line_start,
+                self.start - open_line_start,
+            ));
         }
 
         // Read the closing "
         self.advance();
 
-        let token_str = self.source.get(self.start + 1..self.current - 1).unwrap();
-
-        self.tokens
-            .push(Token::string(self.line, self.current, token_str));
-
-        Ok(())
+        Ok(Token::string(self.line, self.start, self.current, &decoded))
     }
 
-    fn scan_number(&mut self) -> Result<(), LoxError> {
+    fn scan_number(&mut self) -> Token {
         while self.peek().is_digit(10) {
             self.advance();
         }
@@ -231,41 +403,68 @@ impl Scanner {
             }
         }
 
-        self.tokens.push(Token::number(
+        Token::number(
             self.line,
             self.start,
             self.current,
-            self.source
-                .get(self.start..self.current)
-                .unwrap()
-                .parse::<f64>()
-                .ok()
-                .unwrap(),
-        ));
-
-        Ok(())
+            self.slice(self.start, self.current).parse::<f64>().unwrap(),
+        )
     }
 
-    fn scan_identifier(&mut self) {
+    fn scan_identifier(&mut self) -> Token {
         while self.peek().is_alphanumeric() || self.peek() == '_' {
             self.advance();
         }
 
-        let substr = self.source.get(self.start..self.current).unwrap();
+        let substr = self.slice(self.start, self.current);
 
-        let token = match RESERVED_IDENTIFIERS.get(substr) {
-            Some(&token_type) => {
-                Token::identifier(self.line, self.start, self.current, token_type, substr)
-            }
+        match RESERVED_IDENTIFIERS.get(&substr) {
+            Some(&token_type) => Token::identifier(self.line, self.start, self.current, token_type, &substr),
             None => Token::identifier(
                 self.line,
                 self.start,
                 self.current,
                 TokenType::Identifier,
-                substr,
+                &substr,
             ),
-        };
+        }
+    }
+}
+
+/// Adapts a `Scanner` into a pull-based token stream so callers such as a
+/// single-pass compiler can `for token in scanner` instead of driving
+/// `next_token` by hand. Yields one final `Eof` token and then stops.
+pub struct Tokens<'a> {
+    scanner: &'a mut Scanner,
+    done: bool,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Token, LoxResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.scanner.next_token() {
+            Ok(token) => {
+                if token.ttype == TokenType::Eof {
+                    self.done = true;
+                }
+                Some(Ok(token))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
 
-        self.tokens.push(token);
+impl Scanner {
+    /// Returns an iterator that pulls tokens one at a time via `next_token`.
+    pub fn iter_tokens(&mut self) -> Tokens<'_> {
+        Tokens {
+            scanner: self,
+            done: false,
+        }
     }
 }
@@ -0,0 +1,51 @@
+use crate::expr::Expr;
+use crate::token::Token;
+
+/**
+ * A statement produced by the parser and executed by the interpreter.
+ */
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    Block {
+        statements: Vec<Stmt>,
+    },
+    Break {
+        keyword: Token,
+    },
+    Continue {
+        keyword: Token,
+    },
+    Expression {
+        expression: Expr,
+    },
+    Function {
+        name: Token,
+        params: Vec<Token>,
+        body: Vec<Stmt>,
+    },
+    If {
+        condition: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Box<Option<Stmt>>,
+    },
+    Print {
+        expression: Expr,
+    },
+    Return {
+        keyword: Token,
+        value: Option<Expr>,
+    },
+    Var {
+        name: Token,
+        initializer: Option<Expr>,
+    },
+    While {
+        condition: Expr,
+        body: Box<Stmt>,
+        /// The `for`-loop step expression, if this `While` is the
+        /// desugared form of a `for`. Owned by the loop node itself (rather
+        /// than appended to the body) so that a `continue` jumping past the
+        /// rest of the body still runs it on every iteration.
+        increment: Option<Expr>,
+    },
+}
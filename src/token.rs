@@ -1,31 +1,56 @@
+use crate::callable::Callable;
+use crate::interner::Symbol;
 use crate::token_type::*;
 use std::fmt;
 
+use gcmodule::{Trace, Tracer};
 use lazy_static::lazy_static;
 lazy_static! {}
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Object {
     Num(f64),
-    Str(String),
+    /// Interned text: identical literals/identifiers share one allocation
+    /// and compare in O(1) instead of walking both strings.
+    Str(Symbol),
     Nil,
     True,
     False,
+    Callable(Callable),
+}
+
+/// Only `Callable` can reach back into a captured `Environment`, so every
+/// other variant is a trace no-op.
+impl Trace for Object {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Object::Callable(callable) = self {
+            callable.trace(tracer);
+        }
+    }
+}
+
+impl Object {
+    /// Interns `s` and wraps it as a `Str`, the only way to build one from
+    /// a plain `&str`.
+    pub fn str(s: &str) -> Object {
+        Object::Str(Symbol::intern(s))
+    }
 }
 
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Num(x) => write!(f, "{}", x),
-            Self::Str(s) => write!(f, "\"{}\"", s),
+            Self::Str(s) => write!(f, "\"{}\"", s.resolve()),
             Self::Nil => write!(f, "nil"),
             Self::True => write!(f, "true"),
             Self::False => write!(f, "false"),
+            Self::Callable(c) => write!(f, "<fn {}>", c.name()),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Token {
     pub ttype: TokenType,
     pub lexeme: String,
@@ -47,6 +72,23 @@ impl Token {
         }
     }
 
+    /// Returns the source line this token was scanned from.
+    pub fn line(&self) -> usize {
+        self.src_line
+    }
+
+    /// Returns `(line, src_start, src_end)`, the span diagnostics need to
+    /// underline exactly the characters this token covers.
+    pub fn span(&self) -> (usize, usize, usize) {
+        (self.src_line, self.src_start, self.src_end)
+    }
+
+    /// Returns a `"[line N]"` prefix for error messages, pointing at this
+    /// token's source line.
+    pub fn location(&self) -> String {
+        format!("[line {}]", self.src_line)
+    }
+
     pub fn dup(&self) -> Token {
         Token {
             ttype: self.ttype,
@@ -267,14 +309,14 @@ impl Token {
         }
     }
 
-    pub fn string(src_line: usize, src_at: usize, s: &str) -> Token {
+    pub fn string(src_line: usize, src_start: usize, src_end: usize, s: &str) -> Token {
         Token {
             ttype: TokenType::String,
             lexeme: "".to_string(),
-            literal: Some(Object::Str(s.to_string())),
+            literal: Some(Object::str(s)),
             src_line,
-            src_start: src_at,
-            src_end: src_at + s.len(),
+            src_start,
+            src_end,
         }
     }
 
@@ -289,13 +331,7 @@ impl Token {
         }
     }
 
-    pub fn identifier(
-        src_line: usize,
-        src_start: usize,
-        src_end: usize,
-        ttype: TokenType,
-        l: &str,
-    ) -> Token {
+    pub fn identifier(src_line: usize, src_start: usize, src_end: usize, ttype: TokenType, l: &str) -> Token {
         Token {
             ttype,
             lexeme: l.to_string(),
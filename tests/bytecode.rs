@@ -0,0 +1,34 @@
+use rs_lox_tw::bytecode::{Compiler, Vm};
+use rs_lox_tw::scanner::Scanner;
+use rs_lox_tw::token::Object;
+
+fn run(source: &str) -> Vm {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens().expect("source should scan cleanly");
+
+    let chunk = Compiler::new(tokens).compile().expect("source should compile cleanly");
+
+    let mut vm = Vm::new();
+    vm.run(&chunk).expect("chunk should run cleanly");
+    vm
+}
+
+#[test]
+fn test_arithmetic_respects_precedence() {
+    // 2 * 3 = 6, 4 / 2 = 2, so x = 1 + 6 - 2 = 5.
+    let vm = run("x = 1 + 2 * 3 - 4 / 2;");
+    assert_eq!(vm.global("x"), Some(Object::Num(5.0)));
+}
+
+#[test]
+fn test_global_var_define_get_set() {
+    let vm = run("x = 1; y = x + 1; x = y + 1;");
+    assert_eq!(vm.global("x"), Some(Object::Num(3.0)));
+    assert_eq!(vm.global("y"), Some(Object::Num(2.0)));
+}
+
+#[test]
+fn test_print_statement_pops_its_operand() {
+    let vm = run("print 1 + 1;");
+    assert!(vm.stack().is_empty());
+}
@@ -0,0 +1,80 @@
+use rs_lox_tw::scanner::Scanner;
+use rs_lox_tw::token_type::TokenType;
+
+#[test]
+fn test_scan_tokens_large_input() {
+    // Regression guard for the O(n^2) `chars().nth()` scanning bug: this
+    // should scan quickly and still produce the expected token stream.
+    let statement_count = 20_000;
+    let source = "var a = 1;\n".repeat(statement_count);
+
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens().expect("large input should scan cleanly");
+
+    // Each repeated statement yields 5 tokens (var, a, =, 1, ;), plus the
+    // trailing Eof token.
+    assert_eq!(tokens.len(), statement_count * 5 + 1);
+    assert_eq!(tokens.last().unwrap().ttype, TokenType::Eof);
+}
+
+#[test]
+fn test_scan_nested_block_comments() {
+    let source = "/* outer /* inner */ still comment */ var a = 1;";
+
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner
+        .scan_tokens()
+        .expect("nested block comment should scan cleanly");
+
+    // var, a, =, 1, ;, plus the trailing Eof token.
+    assert_eq!(tokens.len(), 6);
+    assert_eq!(tokens.first().unwrap().ttype, TokenType::Var);
+}
+
+#[test]
+fn test_scan_unterminated_block_comment_errors() {
+    let source = "/* never closed";
+
+    let mut scanner = Scanner::new(source.to_string());
+    assert!(scanner.scan_tokens().is_err());
+}
+
+#[test]
+fn test_scan_string_span_covers_quotes() {
+    let source = r#""ab""#;
+
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens().expect("string should scan cleanly");
+
+    // The span should cover the whole source lexeme, opening and closing
+    // quotes included, not just the unescaped text inside it.
+    assert_eq!(tokens.first().unwrap().span(), (1, 0, source.chars().count()));
+}
+
+#[test]
+fn test_scan_escaped_string_span_matches_source_not_decoded_length() {
+    // Decodes to "a\nb" (3 chars), but the source lexeme itself is 6 chars
+    // ('"', 'a', '\\', 'n', 'b', '"') -- the span must track the latter.
+    let source = r#""a\nb""#;
+
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens().expect("escaped string should scan cleanly");
+
+    let (_, start, end) = tokens.first().unwrap().span();
+    assert_eq!(end - start, source.chars().count());
+}
+
+#[test]
+fn test_scan_invalid_escape_resyncs_past_the_escape() {
+    // After the invalid `\z` escape errors out, the scanner should resume
+    // right after the 'z', not re-see it as the start of the next token --
+    // so `hello` (not `zhello`) is scanned as an identifier.
+    let source = r#""\zhello" "#;
+
+    let mut scanner = Scanner::new(source.to_string());
+    assert!(scanner.next_token().is_err());
+
+    let next = scanner.next_token().expect("should resync past the bad escape");
+    assert_eq!(next.ttype, TokenType::Identifier);
+    assert_eq!(next.lexeme, "hello");
+}